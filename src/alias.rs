@@ -0,0 +1,204 @@
+use crate::domain::declared_values;
+use crate::symbols::{SymbolKind, SymbolTable};
+use flatzinc::*;
+
+/// Records a declaration like `var int: x = y;` as alias of the representative of `y`
+/// and removes the assignment, returns the representative.
+/// If the domain of `x` does not contain the domain of the representative,
+/// the assignment is kept, as it restricts the representative, and only refers to it instead.
+/// References to aliases in the assignment of an array are replaced by their representatives.
+pub(crate) fn resolve_var_decl_item(
+    item: &mut VarDeclItem,
+    symbols: &mut SymbolTable,
+) -> Option<String> {
+    let values = declared_values(item);
+    let (id, target) = match item {
+        VarDeclItem::Bool {
+            id,
+            expr: Some(BoolExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::Int {
+            id,
+            expr: Some(IntExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::IntInRange {
+            id,
+            expr: Some(IntExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::IntInSet {
+            id,
+            expr: Some(IntExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::Float {
+            id,
+            expr: Some(FloatExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::BoundedFloat {
+            id,
+            expr: Some(FloatExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::SetOfInt {
+            id,
+            expr: Some(SetExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::SubSetOfIntRange {
+            id,
+            expr: Some(SetExpr::VarParIdentifier(target)),
+            ..
+        }
+        | VarDeclItem::SubSetOfIntSet {
+            id,
+            expr: Some(SetExpr::VarParIdentifier(target)),
+            ..
+        } => (id, target),
+        VarDeclItem::ArrayOfBool {
+            array_expr: Some(ArrayOfBoolExpr::Array(v)),
+            ..
+        } => {
+            v.iter_mut().for_each(|e| bool_expr(e, symbols));
+            return None;
+        }
+        VarDeclItem::ArrayOfInt {
+            array_expr: Some(ArrayOfIntExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfIntInRange {
+            array_expr: Some(ArrayOfIntExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfIntInSet {
+            array_expr: Some(ArrayOfIntExpr::Array(v)),
+            ..
+        } => {
+            v.iter_mut().for_each(|e| int_expr(e, symbols));
+            return None;
+        }
+        VarDeclItem::ArrayOfFloat {
+            array_expr: Some(ArrayOfFloatExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfBoundedFloat {
+            array_expr: Some(ArrayOfFloatExpr::Array(v)),
+            ..
+        } => {
+            v.iter_mut().for_each(|e| float_expr(e, symbols));
+            return None;
+        }
+        VarDeclItem::ArrayOfSet {
+            array_expr: Some(ArrayOfSetExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfSubSetOfIntRange {
+            array_expr: Some(ArrayOfSetExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfSubSetOfIntSet {
+            array_expr: Some(ArrayOfSetExpr::Array(v)),
+            ..
+        } => {
+            v.iter_mut().for_each(|e| set_expr(e, symbols));
+            return None;
+        }
+        _ => return None,
+    };
+    match symbols.get(target) {
+        Some(s) if s.kind == SymbolKind::Variable => {}
+        _ => return None,
+    }
+    let representative = symbols.representative(target).to_string();
+    if !symbols
+        .domain(&representative)
+        .is_some_and(|domain| values.contains(domain))
+    {
+        rename(target, symbols);
+        return None;
+    }
+    symbols.declare_alias(id, &representative);
+    clear_assignment(item);
+    Some(representative)
+}
+fn clear_assignment(item: &mut VarDeclItem) {
+    match item {
+        VarDeclItem::Bool { expr, .. } => *expr = None,
+        VarDeclItem::Int { expr, .. }
+        | VarDeclItem::IntInRange { expr, .. }
+        | VarDeclItem::IntInSet { expr, .. } => *expr = None,
+        VarDeclItem::Float { expr, .. } | VarDeclItem::BoundedFloat { expr, .. } => *expr = None,
+        VarDeclItem::SetOfInt { expr, .. }
+        | VarDeclItem::SubSetOfIntRange { expr, .. }
+        | VarDeclItem::SubSetOfIntSet { expr, .. } => *expr = None,
+        _ => {}
+    }
+}
+/// Replaces references to aliases in the arguments of a constraint by their representatives
+pub(crate) fn resolve_constraint(c: &mut ConstraintItem, symbols: &SymbolTable) {
+    for e in &mut c.exprs {
+        match e {
+            Expr::VarParIdentifier(id) => rename(id, symbols),
+            Expr::Set(sl) => set_literal_expr(sl, symbols),
+            Expr::ArrayOfBool(v) => v.iter_mut().for_each(|e| bool_expr(e, symbols)),
+            Expr::ArrayOfInt(v) => v.iter_mut().for_each(|e| int_expr(e, symbols)),
+            Expr::ArrayOfFloat(v) => v.iter_mut().for_each(|e| float_expr(e, symbols)),
+            Expr::ArrayOfSet(v) => v.iter_mut().for_each(|e| set_expr(e, symbols)),
+            _ => {}
+        }
+    }
+}
+/// Replaces a reference to an alias in the objective by its representative
+pub(crate) fn resolve_solve_item(i: &mut SolveItem, symbols: &SymbolTable) {
+    match &mut i.goal {
+        Goal::Satisfy => {}
+        Goal::OptimizeBool(_, e) => bool_expr(e, symbols),
+        Goal::OptimizeInt(_, e) => int_expr(e, symbols),
+        Goal::OptimizeFloat(_, e) => float_expr(e, symbols),
+        Goal::OptimizeSet(_, e) => set_expr(e, symbols),
+    }
+}
+fn rename(id: &mut String, symbols: &SymbolTable) {
+    let representative = symbols.representative(id);
+    if representative != id {
+        *id = representative.to_string();
+    }
+}
+fn bool_expr(e: &mut BoolExpr, symbols: &SymbolTable) {
+    if let BoolExpr::VarParIdentifier(id) = e {
+        rename(id, symbols);
+    }
+}
+fn int_expr(e: &mut IntExpr, symbols: &SymbolTable) {
+    if let IntExpr::VarParIdentifier(id) = e {
+        rename(id, symbols);
+    }
+}
+fn float_expr(e: &mut FloatExpr, symbols: &SymbolTable) {
+    if let FloatExpr::VarParIdentifier(id) = e {
+        rename(id, symbols);
+    }
+}
+fn set_expr(e: &mut SetExpr, symbols: &SymbolTable) {
+    match e {
+        SetExpr::VarParIdentifier(id) => rename(id, symbols),
+        SetExpr::Set(sl) => set_literal_expr(sl, symbols),
+    }
+}
+fn set_literal_expr(sl: &mut SetLiteralExpr, symbols: &SymbolTable) {
+    match sl {
+        SetLiteralExpr::IntInRange(lb, ub) => {
+            int_expr(lb, symbols);
+            int_expr(ub, symbols);
+        }
+        SetLiteralExpr::BoundedFloat(lb, ub) => {
+            float_expr(lb, symbols);
+            float_expr(ub, symbols);
+        }
+        SetLiteralExpr::SetInts(v) => v.iter_mut().for_each(|e| int_expr(e, symbols)),
+        SetLiteralExpr::SetFloats(v) => v.iter_mut().for_each(|e| float_expr(e, symbols)),
+    }
+}
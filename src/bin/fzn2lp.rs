@@ -1,11 +1,16 @@
-use anyhow::Result;
-use fzn2lp::write_fz_stmt;
-use fzn2lp::FlatZincError;
+use anyhow::{Context, Result};
+use flatzinc::{Annotation, FzStmt, Goal, SolveItem};
+use fzn2lp::{parse_fz_stmt, write_fz_stmt, write_stmt};
+use fzn2lp::{
+    Category, CategorySinks, Components, Config, DuplicatePolicy, Elimination, Features,
+    FlatZincError, FloatMode, IdentifierMode, IncidenceGraph, OverflowPolicy, Presolve,
+    ProgramSink, Sink, Slice, State, Stats, SupportReport, SupportedConstraints,
+};
 use log::error;
 use std::fs;
 use std::{
     io::{self, prelude::*},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use structopt::StructOpt;
 
@@ -16,6 +21,188 @@ struct Opt {
     /// Input file in flatzinc format
     #[structopt(name = "FILE", parse(from_os_str))]
     file: Option<PathBuf>,
+
+    /// Representation of identifiers, either `string` or `constant`.
+    /// In constant mode identifiers that are valid clingo constants are not quoted
+    #[structopt(
+        short = "i",
+        long = "identifiers",
+        default_value = "string",
+        possible_values = &["string", "constant"]
+    )]
+    identifiers: IdentifierMode,
+
+    /// Representation of floats, either `string`, `scaled` or `rational`
+    #[structopt(
+        short = "f",
+        long = "floats",
+        default_value = "string",
+        possible_values = &["string", "scaled", "rational"]
+    )]
+    floats: String,
+
+    /// Factor by which floats are multiplied in `scaled` mode
+    #[structopt(long = "float-scale", default_value = "1000")]
+    float_scale: u64,
+
+    /// Treatment of integers outside of the target range, either `error`, `warn`, `string` or `split`
+    #[structopt(
+        long = "int-overflow",
+        default_value = "warn",
+        possible_values = &["error", "warn", "string", "split"]
+    )]
+    int_overflow: OverflowPolicy,
+
+    /// Treatment of constraints identical to an earlier constraint, either `keep`,
+    /// `report` to emit `duplicate_of(C,First)`, or `remove`
+    #[structopt(
+        long = "duplicates",
+        default_value = "keep",
+        possible_values = &["keep", "report", "remove"]
+    )]
+    duplicates: DuplicatePolicy,
+
+    /// Smallest integer supported by the target system
+    #[structopt(
+        long = "int-min",
+        default_value = "-2147483648",
+        allow_hyphen_values = true
+    )]
+    int_min: i128,
+
+    /// Largest integer supported by the target system
+    #[structopt(
+        long = "int-max",
+        default_value = "2147483647",
+        allow_hyphen_values = true
+    )]
+    int_max: i128,
+
+    /// Write the facts of each category to a separate file in this directory,
+    /// together with an index file `index.lp` including all of them
+    #[structopt(short = "o", long = "output-dir", parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+
+    /// Wrap the facts of each category in a `#program fzn_<category>.` block
+    #[structopt(short = "p", long = "programs")]
+    programs: bool,
+
+    /// Split the constraints into numbered `#program fzn_constraints_<n>.` blocks
+    /// of at most this many constraints, implies --programs
+    #[structopt(long = "constraint-batch")]
+    constraint_batch: Option<usize>,
+
+    /// Emit `#show` directives for all output variables using this atom template,
+    /// e.g. `val(X,V)`, where `X` is replaced by the identifier of the variable
+    #[structopt(short = "s", long = "show")]
+    show: Option<String>,
+
+    /// Emit the type of every constraint argument as `constraint_type_at(C,Pos,Type)`
+    #[structopt(short = "t", long = "typed")]
+    typed: bool,
+
+    /// Replace references to parameters in constraints and variable assignments by their values
+    #[structopt(long = "inline-parameters")]
+    inline_parameters: bool,
+
+    /// Replace references to variable arrays in constraints by their elements
+    #[structopt(long = "expand-arrays")]
+    expand_arrays: bool,
+
+    /// Replace variables assigned to other variables by a representative
+    /// and emit `alias(Variable,Representative)` facts
+    #[structopt(short = "a", long = "aliases")]
+    aliases: bool,
+
+    /// Emit `variable_domain(Variable,Value)` for every value of integer variables
+    /// whose domain has at most this many values
+    #[structopt(long = "enumerate-domains")]
+    enumerate_domains: Option<u64>,
+
+    /// Emit `constraint_set_element(C,Pos,Value)` for every value of integer set arguments
+    /// of constraints with at most this many values
+    #[structopt(long = "enumerate-sets")]
+    enumerate_sets: Option<u64>,
+
+    /// Report inconsistencies of the model, like values outside of a domain
+    /// or undefined identifiers, as warnings
+    #[structopt(long = "lenient")]
+    lenient: bool,
+
+    /// Do not report references to undeclared identifiers and duplicate declarations
+    #[structopt(long = "no-identifier-checks")]
+    no_identifier_checks: bool,
+
+    /// Check the arity and argument types of constraints
+    /// against the declared predicates and the FlatZinc builtins
+    #[structopt(long = "check-signatures")]
+    check_signatures: bool,
+
+    /// Fix variables and tighten their domains from simple constraints before converting,
+    /// and drop the constraints this satisfies; the deductions are reported on stderr
+    #[structopt(long = "presolve")]
+    presolve: bool,
+
+    /// Write the presolved model as FlatZinc instead of converting it
+    #[structopt(
+        long = "presolve-fzn",
+        requires = "presolve",
+        conflicts_with_all = &["eliminate", "slice", "output-dir", "programs", "constraint-batch"]
+    )]
+    presolve_fzn: bool,
+
+    /// Drop parameters and introduced variables that nothing refers to,
+    /// the removed declarations are reported on stderr
+    #[structopt(long = "eliminate")]
+    eliminate: bool,
+
+    /// Emit `constraint_variable(C,Variable)` for every variable referenced by a constraint
+    #[structopt(long = "incidence")]
+    incidence: bool,
+
+    /// Instead of converting the model, list all constraints not supported by an encoding.
+    /// The supported constraints are read from the given files, which are either encodings
+    /// (`.lp`), whose `constraint(C,"name")` patterns are extracted, or lists of names
+    #[structopt(long = "supported", parse(from_os_str), number_of_values = 1)]
+    supported: Vec<PathBuf>,
+
+    /// Instead of converting the model, print statistics about its size,
+    /// either as `text` or as `json`
+    #[structopt(long = "stats", possible_values = &["text", "json"])]
+    stats: Option<String>,
+
+    /// Instead of converting the model, print numeric features for algorithm selection,
+    /// either as `csv` or as `json`
+    #[structopt(long = "features", possible_values = &["csv", "json"])]
+    features: Option<String>,
+
+    /// Instead of converting the model, print the graph linking constraints and variables,
+    /// either in the Graphviz `dot` format or as `graphml`
+    #[structopt(long = "graph", possible_values = &["dot", "graphml"])]
+    graph: Option<String>,
+
+    /// Instead of converting the model, emit `component(Id,var|constraint,Name)`
+    /// for the connected components of the graph linking constraints and variables
+    #[structopt(long = "components")]
+    components: bool,
+
+    /// Convert every connected component into a separate file `component_<n>.lp`
+    /// in this directory
+    #[structopt(long = "split-components", parse(from_os_str))]
+    split_components: Option<PathBuf>,
+
+    /// Convert only the statements that can influence the given variable,
+    /// may be given several times
+    #[structopt(
+        long = "slice",
+        number_of_values = 1,
+        conflicts_with_all = &["presolve", "eliminate", "output-dir", "programs", "constraint-batch"]
+    )]
+    slice: Vec<String>,
+
+    /// Write the slice as FlatZinc instead of converting it
+    #[structopt(long = "slice-fzn", requires = "slice")]
+    slice_fzn: bool,
 }
 
 pub enum Reader<'a> {
@@ -61,7 +248,7 @@ fn run() -> Result<()> {
     let opt = Opt::from_args();
 
     let stdin = io::stdin();
-    let input = match opt.file {
+    let input = match &opt.file {
         Some(path) => {
             let file = fs::File::open(path)?;
             Reader::File(io::BufReader::new(file))
@@ -72,14 +259,349 @@ fn run() -> Result<()> {
         }
     };
 
-    let mut out = std::io::stdout();
-    let mut level = 1;
-    let mut constraint_counter = 1;
-    for line in input.lines() {
-        write_fz_stmt(&mut out, &line?, &mut constraint_counter, &mut level)?;
+    let config = Config {
+        identifiers: opt.identifiers,
+        floats: match opt.floats.as_str() {
+            "scaled" => FloatMode::Scaled(opt.float_scale),
+            "rational" => FloatMode::Rational,
+            _ => FloatMode::String,
+        },
+        int_range: (opt.int_min, opt.int_max),
+        overflow: opt.int_overflow,
+        show: opt.show.clone(),
+        typed_constraints: opt.typed,
+        inline_parameters: opt.inline_parameters,
+        expand_arrays: opt.expand_arrays,
+        resolve_aliases: opt.aliases,
+        domain_threshold: opt.enumerate_domains,
+        set_threshold: opt.enumerate_sets,
+        lenient: opt.lenient,
+        check_identifiers: !opt.no_identifier_checks,
+        check_signatures: opt.check_signatures,
+        incidence: opt.incidence,
+        duplicates: opt.duplicates,
+    };
+    if let Some(format) = &opt.stats {
+        return write_stats(input, format == "json");
+    }
+    if let Some(format) = &opt.features {
+        return write_features(input, format == "json");
+    }
+    if let Some(format) = &opt.graph {
+        return write_graph(input, format == "graphml");
+    }
+    if opt.components {
+        return write_components(input, &config);
+    }
+    if let Some(dir) = &opt.split_components {
+        return split_components(input, dir, &config);
+    }
+    if opt.presolve_fzn {
+        let Model { stmts, lines, .. } = read_model(input)?;
+        let presolve = Presolve::new(&stmts);
+        presolve.write_log(io::stderr())?;
+        presolve.write_fzn(io::stdout(), &stmts, &lines)?;
+        return Ok(());
+    }
+    if !opt.slice.is_empty() {
+        return write_slice(input, &opt, &config);
+    }
+    if !opt.supported.is_empty() {
+        return check_supported(input, &opt.supported);
+    }
+    match &opt.output_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            let out = CategorySinks::new(|category| {
+                let file = fs::File::create(dir.join(format!("{}.lp", category.name())))?;
+                Ok(io::BufWriter::new(file))
+            })?;
+            let mut out = convert(input, out, &opt, &config)?;
+            out.flush()?;
+            Category::write_index(fs::File::create(dir.join("index.lp"))?)?;
+            Ok(())
+        }
+        None => {
+            convert(input, io::stdout(), &opt, &config)?;
+            Ok(())
+        }
+    }
+}
+fn convert<S: Sink>(input: impl BufRead, out: S, opt: &Opt, config: &Config) -> Result<S> {
+    if opt.programs || opt.constraint_batch.is_some() {
+        let mut out = ProgramSink::new(out, opt.constraint_batch);
+        convert_lines(input, &mut out, opt, config)?;
+        Ok(out.into_inner())
+    } else {
+        let mut out = out;
+        convert_lines(input, &mut out, opt, config)?;
+        Ok(out)
     }
-    if level < 5 {
+}
+fn convert_lines(
+    input: impl BufRead,
+    out: &mut impl Sink,
+    opt: &Opt,
+    config: &Config,
+) -> Result<()> {
+    let mut state = State {
+        constraint_counter: 1,
+        level: 1,
+        ..Default::default()
+    };
+    if opt.presolve || opt.eliminate {
+        let Model {
+            mut stmts,
+            par_annos,
+            ..
+        } = read_model(input)?;
+        // numbers of the constraints in the whole model
+        let numbers: Vec<usize> = stmts
+            .iter()
+            .scan(1, |counter, stmt| {
+                if let FzStmt::Constraint(_) = stmt {
+                    *counter += 1;
+                }
+                Some(*counter)
+            })
+            .collect();
+        let mut removed = vec![false; stmts.len()];
+        if opt.presolve {
+            let presolve = Presolve::new(&stmts);
+            presolve.write_log(io::stderr())?;
+            for (i, stmt) in stmts.iter_mut().enumerate() {
+                presolve.apply(i, stmt);
+                if presolve.removes(i) {
+                    // removed constraints must not keep declarations alive
+                    *stmt = FzStmt::Comment(String::new());
+                    removed[i] = true;
+                }
+            }
+        }
+        if opt.eliminate {
+            let elimination = Elimination::new(&stmts, &par_annos);
+            elimination.write_report(io::stderr())?;
+            for (i, removed) in removed.iter_mut().enumerate() {
+                *removed |= elimination.removes(i);
+            }
+        }
+        for (i, (stmt, annos)) in stmts.into_iter().zip(par_annos).enumerate() {
+            if removed[i] {
+                continue;
+            }
+            // keep the numbering of the constraints of the whole model
+            state.constraint_counter = numbers[i] - 1;
+            write_stmt(out, stmt, &annos, &mut state, config)
+                .with_context(|| format!("Error in line {}", i + 1))?;
+        }
+    } else {
+        for (nr, line) in input.lines().enumerate() {
+            write_fz_stmt(out, &line?, &mut state, config)
+                .with_context(|| format!("Error in line {}", nr + 1))?;
+        }
+    }
+    if state.level < 5 {
         return Err(FlatZincError::NoSolveItem.into());
     }
     Ok(())
 }
+fn check_supported(input: impl BufRead, files: &[PathBuf]) -> Result<()> {
+    let mut supported = SupportedConstraints::default();
+    for path in files {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == "lp") {
+            supported.add_encoding(&text);
+        } else {
+            supported.add_list(&text);
+        }
+    }
+    let mut report = SupportReport::default();
+    for (nr, line) in input.lines().enumerate() {
+        let (stmt, _) =
+            parse_fz_stmt(&line?).with_context(|| format!("Error in line {}", nr + 1))?;
+        report.check(&stmt, nr + 1, &supported);
+    }
+    report.write(io::stdout())?;
+    let count = report.unsupported().len();
+    if count > 0 {
+        return Err(FlatZincError::UnsupportedConstraints { count }.into());
+    }
+    Ok(())
+}
+fn write_stats(input: impl BufRead, json: bool) -> Result<()> {
+    let mut stats = Stats::default();
+    for (nr, line) in input.lines().enumerate() {
+        let (stmt, par_annos) =
+            parse_fz_stmt(&line?).with_context(|| format!("Error in line {}", nr + 1))?;
+        stats.add(&stmt, &par_annos);
+    }
+    if json {
+        stats.write_json(io::stdout())?;
+    } else {
+        stats.write_text(io::stdout())?;
+    }
+    Ok(())
+}
+fn write_features(input: impl BufRead, json: bool) -> Result<()> {
+    let mut features = Features::default();
+    for (nr, line) in input.lines().enumerate() {
+        let (stmt, _) =
+            parse_fz_stmt(&line?).with_context(|| format!("Error in line {}", nr + 1))?;
+        features.add(&stmt);
+    }
+    if json {
+        features.write_json(io::stdout())?;
+    } else {
+        features.write_csv(io::stdout())?;
+    }
+    Ok(())
+}
+fn write_graph(input: impl BufRead, graphml: bool) -> Result<()> {
+    // constraints are numbered like in the conversion
+    let mut graph = IncidenceGraph::new(1);
+    for (nr, line) in input.lines().enumerate() {
+        let (stmt, _) =
+            parse_fz_stmt(&line?).with_context(|| format!("Error in line {}", nr + 1))?;
+        graph.add(&stmt);
+    }
+    if graphml {
+        graph.write_graphml(io::stdout())?;
+    } else {
+        graph.write_dot(io::stdout())?;
+    }
+    Ok(())
+}
+fn write_components(input: impl BufRead, config: &Config) -> Result<()> {
+    // constraints are numbered like in the conversion
+    let mut components = Components::new(1);
+    for (nr, line) in input.lines().enumerate() {
+        let (stmt, _) =
+            parse_fz_stmt(&line?).with_context(|| format!("Error in line {}", nr + 1))?;
+        components.add(&stmt);
+    }
+    components.assign().write_facts(io::stdout(), config)?;
+    Ok(())
+}
+fn split_components(input: impl BufRead, dir: &Path, config: &Config) -> Result<()> {
+    let mut stmts = vec![];
+    let mut components = Components::new(1);
+    for (nr, line) in input.lines().enumerate() {
+        let (stmt, par_annos) =
+            parse_fz_stmt(&line?).with_context(|| format!("Error in line {}", nr + 1))?;
+        components.add(&stmt);
+        stmts.push((nr + 1, stmt, par_annos));
+    }
+    let assignment = components.assign();
+    fs::create_dir_all(dir)?;
+    let mut outputs = vec![];
+    for k in 1..=assignment.count {
+        let file = fs::File::create(dir.join(format!("component_{}.lp", k)))?;
+        let state = State {
+            constraint_counter: 1,
+            level: 1,
+            ..Default::default()
+        };
+        outputs.push((io::BufWriter::new(file), state));
+    }
+    let mut counter = 1;
+    for (nr, stmt, par_annos) in stmts {
+        if let FzStmt::Constraint(_) = stmt {
+            counter += 1;
+        }
+        match assignment.stmt_component(&stmt, counter) {
+            Some(k) => {
+                let (out, state) = &mut outputs[k - 1];
+                // keep the numbering of the constraints of the whole model
+                state.constraint_counter = counter - 1;
+                write_stmt(out, stmt, &par_annos, state, config)
+                    .with_context(|| format!("Error in line {}", nr + 1))?;
+            }
+            None => {
+                for (k, (out, state)) in outputs.iter_mut().enumerate() {
+                    let stmt = match &stmt {
+                        // search annotations may refer to other components
+                        FzStmt::SolveItem(i) if assignment.objective == Some(k + 1) => {
+                            FzStmt::SolveItem(SolveItem {
+                                goal: i.goal.clone(),
+                                annotations: vec![],
+                            })
+                        }
+                        FzStmt::SolveItem(_) => FzStmt::SolveItem(SolveItem {
+                            goal: Goal::Satisfy,
+                            annotations: vec![],
+                        }),
+                        stmt => stmt.clone(),
+                    };
+                    write_stmt(out, stmt, &par_annos, state, config)
+                        .with_context(|| format!("Error in line {}", nr + 1))?;
+                }
+            }
+        }
+    }
+    for (out, _) in &mut outputs {
+        out.flush()?;
+    }
+    Ok(())
+}
+fn write_slice(input: impl BufRead, opt: &Opt, config: &Config) -> Result<()> {
+    let Model {
+        stmts,
+        par_annos,
+        lines,
+    } = read_model(input)?;
+    let slice = Slice::new(&stmts, &opt.slice)?;
+    if opt.slice_fzn {
+        slice.write_fzn(io::stdout(), &stmts, &lines)?;
+        return Ok(());
+    }
+    let mut out = io::stdout();
+    let mut state = State {
+        constraint_counter: 1,
+        level: 1,
+        ..Default::default()
+    };
+    let mut counter = 1;
+    for (i, (stmt, annos)) in stmts.into_iter().zip(par_annos).enumerate() {
+        if let FzStmt::Constraint(_) = stmt {
+            counter += 1;
+        }
+        if !slice.contains(i) {
+            continue;
+        }
+        let stmt = match stmt {
+            FzStmt::SolveItem(item) => FzStmt::SolveItem(slice.solve_item(&item)),
+            stmt => stmt,
+        };
+        // keep the numbering of the constraints of the whole model
+        state.constraint_counter = counter - 1;
+        write_stmt(&mut out, stmt, &annos, &mut state, config)
+            .with_context(|| format!("Error in line {}", i + 1))?;
+    }
+    Ok(())
+}
+/// Statements of a model, with the annotations of the parameter declarations
+/// and the lines they were parsed from
+struct Model {
+    stmts: Vec<FzStmt>,
+    par_annos: Vec<Vec<Annotation>>,
+    lines: Vec<String>,
+}
+fn read_model(input: impl BufRead) -> Result<Model> {
+    let mut stmts = vec![];
+    let mut par_annos = vec![];
+    let mut lines = vec![];
+    for (nr, line) in input.lines().enumerate() {
+        let line = line?;
+        let (stmt, annos) =
+            parse_fz_stmt(&line).with_context(|| format!("Error in line {}", nr + 1))?;
+        stmts.push(stmt);
+        par_annos.push(annos);
+        lines.push(line);
+    }
+    Ok(Model {
+        stmts,
+        par_annos,
+        lines,
+    })
+}
@@ -0,0 +1,242 @@
+use crate::incidence::{argument_ids, array_element_ids, assigned_id, objective_id};
+use crate::{identifier, var_decl_id, Config};
+use flatzinc::*;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Variable declaration or constraint of a model
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Variable(String),
+    /// Number of the constraint, as used in the conversion
+    Constraint(usize),
+}
+
+/// Collects the graph linking variables and constraints to compute its connected components
+///
+/// Variable arrays are linked to their elements and variables to the variables assigned to them,
+/// so a declaration always belongs to the same component as the variables it refers to.
+#[derive(Debug, Default)]
+pub struct Components {
+    constraint_counter: usize,
+    /// Variable declarations and constraints in the order of the model
+    items: Vec<Item>,
+    /// Item of every declared variable
+    variables: HashMap<String, usize>,
+    /// Union-find forest over the items
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    objective: Option<String>,
+}
+impl Components {
+    /// Creates an empty graph, the constraints are numbered like in the conversion,
+    /// where the counter is incremented before each constraint
+    pub fn new(constraint_counter: usize) -> Self {
+        Components {
+            constraint_counter,
+            ..Default::default()
+        }
+    }
+    pub fn add(&mut self, stmt: &FzStmt) {
+        match stmt {
+            FzStmt::Variable(d) => {
+                let (id, elements) = match array_element_ids(d) {
+                    Some((id, elements)) => (id, elements),
+                    None => (var_decl_id(d), assigned_id(d).into_iter().collect()),
+                };
+                let item = self.push(Item::Variable(id.to_string()));
+                self.variables.insert(id.to_string(), item);
+                self.link(item, elements);
+            }
+            FzStmt::Constraint(c) => {
+                self.constraint_counter += 1;
+                let item = self.push(Item::Constraint(self.constraint_counter));
+                let mut ids = vec![];
+                for e in &c.exprs {
+                    argument_ids(e, &mut ids);
+                }
+                self.link(item, ids);
+            }
+            FzStmt::SolveItem(i) => self.objective = objective_id(&i.goal).map(|id| id.to_string()),
+            _ => {}
+        }
+    }
+    fn push(&mut self, item: Item) -> usize {
+        self.items.push(item);
+        self.parent.push(self.parent.len());
+        self.size.push(1);
+        self.items.len() - 1
+    }
+    /// Joins the component of `item` with the components of the declared variables among `ids`
+    fn link(&mut self, item: usize, ids: Vec<&str>) {
+        for id in ids {
+            if let Some(other) = self.variables.get(id).copied() {
+                self.union(item, other);
+            }
+        }
+    }
+    fn find(&self, mut item: usize) -> usize {
+        while self.parent[item] != item {
+            item = self.parent[item];
+        }
+        item
+    }
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        let (small, large) = if self.size[a] < self.size[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent[small] = large;
+        self.size[large] += self.size[small];
+    }
+    /// Numbers the components from 1 in the order of their first item
+    pub fn assign(&self) -> ComponentAssignment {
+        let mut ids = HashMap::new();
+        let mut labels = Vec::with_capacity(self.items.len());
+        for item in 0..self.items.len() {
+            let next = ids.len() + 1;
+            labels.push(*ids.entry(self.find(item)).or_insert(next));
+        }
+        let mut assignment = ComponentAssignment {
+            count: ids.len(),
+            ..Default::default()
+        };
+        for (item, label) in self.items.iter().zip(labels) {
+            match item {
+                Item::Variable(id) => {
+                    assignment.variables.insert(id.clone(), label);
+                }
+                Item::Constraint(i) => {
+                    assignment.constraints.insert(*i, label);
+                }
+            }
+            assignment.items.push((item.clone(), label));
+        }
+        assignment.objective = self
+            .objective
+            .as_deref()
+            .and_then(|id| assignment.variable(id));
+        assignment
+    }
+}
+
+/// Component of every variable declaration and constraint of a model
+#[derive(Debug, Default)]
+pub struct ComponentAssignment {
+    /// Number of components
+    pub count: usize,
+    /// Component of the objective variable
+    pub objective: Option<usize>,
+    items: Vec<(Item, usize)>,
+    variables: HashMap<String, usize>,
+    constraints: HashMap<usize, usize>,
+}
+impl ComponentAssignment {
+    pub fn variable(&self, id: &str) -> Option<usize> {
+        self.variables.get(id).copied()
+    }
+    /// Returns the component of the constraint with the given number
+    pub fn constraint(&self, i: usize) -> Option<usize> {
+        self.constraints.get(&i).copied()
+    }
+    /// Returns the component of a variable declaration or a constraint with the given number,
+    /// all other statements belong to every component
+    pub fn stmt_component(&self, stmt: &FzStmt, i: usize) -> Option<usize> {
+        match stmt {
+            FzStmt::Variable(d) => self.variable(var_decl_id(d)),
+            FzStmt::Constraint(_) => self.constraint(i),
+            _ => None,
+        }
+    }
+    /// Writes `component(Id,var,Variable)` and `component(Id,constraint,C)` for every item
+    pub fn write_facts(&self, mut out: impl Write, config: &Config) -> io::Result<()> {
+        for (item, label) in &self.items {
+            match item {
+                Item::Variable(id) => {
+                    writeln!(out, "component({},var,{}).", label, identifier(id, config))?
+                }
+                Item::Constraint(i) => writeln!(out, "component({},constraint,c{}).", label, i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_fz_stmt;
+
+    fn components(stmts: &[&str]) -> Components {
+        let mut components = Components::new(0);
+        for stmt in stmts {
+            components.add(&parse_fz_stmt(stmt).unwrap().0);
+        }
+        components
+    }
+
+    #[test]
+    fn test_union_find() {
+        let mut components = Components::default();
+        let items: Vec<_> = (1..=5)
+            .map(|i| components.push(Item::Constraint(i)))
+            .collect();
+        components.union(items[0], items[1]);
+        components.union(items[3], items[4]);
+        assert_eq!(components.find(items[0]), components.find(items[1]));
+        assert_ne!(components.find(items[0]), components.find(items[3]));
+        // the smaller tree is attached to the larger one
+        components.union(items[2], items[1]);
+        assert_eq!(components.find(items[2]), components.find(items[0]));
+        assert_eq!(components.size[components.find(items[0])], 3);
+        components.union(items[4], items[0]);
+        assert!(items
+            .iter()
+            .all(|i| components.find(*i) == components.find(items[0])));
+        assert_eq!(components.size[components.find(items[0])], 5);
+        // joining items of the same component changes nothing
+        components.union(items[1], items[3]);
+        assert_eq!(components.size[components.find(items[0])], 5);
+    }
+    #[test]
+    fn test_assign() {
+        let assignment = components(&[
+            "var 1..3: x;",
+            "var 1..3: y;",
+            "var 1..3: z;",
+            "var 1..3: u;",
+            "var 1..3: v = u;",
+            "array [1..2] of var int: ys = [y,2];",
+            "constraint int_le(x,z);",
+            "constraint all_different_int(ys);",
+            "solve minimize v;",
+        ])
+        .assign();
+        assert_eq!(assignment.count, 3);
+        assert_eq!(assignment.variable("x"), Some(1));
+        assert_eq!(assignment.variable("z"), Some(1));
+        assert_eq!(assignment.constraint(1), Some(1));
+        assert_eq!(assignment.variable("y"), Some(2));
+        assert_eq!(assignment.variable("ys"), Some(2));
+        assert_eq!(assignment.constraint(2), Some(2));
+        assert_eq!(assignment.variable("u"), Some(3));
+        assert_eq!(assignment.variable("v"), Some(3));
+        assert_eq!(assignment.objective, Some(3));
+        assert_eq!(assignment.variable("w"), None);
+    }
+    #[test]
+    fn test_constraint_numbering() {
+        let assignment = components(&["var bool: b;", "constraint bool_eq(b,true);"]).assign();
+        assert_eq!(assignment.constraint(1), Some(1));
+        let mut components = Components::new(1);
+        components.add(&parse_fz_stmt("constraint bool_eq(b,true);").unwrap().0);
+        let assignment = components.assign();
+        assert_eq!(assignment.constraint(1), None);
+        assert_eq!(assignment.constraint(2), Some(1));
+    }
+}
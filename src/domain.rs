@@ -0,0 +1,363 @@
+use crate::{Config, FlatZincError, SymbolTable};
+use anyhow::Result;
+use flatzinc::*;
+use log::warn;
+
+/// Checks that the domain of a variable declaration is not empty
+/// and that the assigned values lie within the domain
+///
+/// Issues are reported as errors, or as warnings in lenient mode.
+/// Values given by parameters are looked up in the symbol table, which only keeps them
+/// if parameters are inlined,
+/// references to other variables are not checked.
+pub(crate) fn check_var_decl_item(
+    item: &VarDeclItem,
+    symbols: &SymbolTable,
+    config: &Config,
+) -> Result<()> {
+    match item {
+        VarDeclItem::Bool { .. }
+        | VarDeclItem::Int { .. }
+        | VarDeclItem::Float { .. }
+        | VarDeclItem::SetOfInt { .. }
+        | VarDeclItem::ArrayOfBool { .. }
+        | VarDeclItem::ArrayOfInt { .. }
+        | VarDeclItem::ArrayOfFloat { .. }
+        | VarDeclItem::ArrayOfSet { .. } => {}
+        VarDeclItem::IntInRange {
+            id, lb, ub, expr, ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntRange(*lb, *ub));
+            if checker.non_empty()? {
+                checker.opt_int_expr(expr, None)?;
+            }
+        }
+        VarDeclItem::IntInSet { id, set, expr, .. } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntSet(set));
+            if checker.non_empty()? {
+                checker.opt_int_expr(expr, None)?;
+            }
+        }
+        VarDeclItem::BoundedFloat {
+            id, lb, ub, expr, ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::FloatRange(*lb, *ub));
+            if checker.non_empty()? {
+                if let Some(e) = expr {
+                    checker.float_expr(e, None)?;
+                }
+            }
+        }
+        VarDeclItem::SubSetOfIntRange {
+            id, lb, ub, expr, ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntRange(*lb, *ub));
+            if let Some(e) = expr {
+                checker.set_expr(e, None)?;
+            }
+        }
+        VarDeclItem::SubSetOfIntSet { id, set, expr, .. } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntSet(set));
+            if let Some(e) = expr {
+                checker.set_expr(e, None)?;
+            }
+        }
+        VarDeclItem::ArrayOfIntInRange {
+            id,
+            lb,
+            ub,
+            ix: IndexSet(len),
+            array_expr,
+            ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntRange(*lb, *ub));
+            if *len == 0 || checker.non_empty()? {
+                for (pos, e) in int_elements(array_expr, symbols).iter().enumerate() {
+                    checker.int_expr(e, Some(pos))?;
+                }
+            }
+        }
+        VarDeclItem::ArrayOfIntInSet {
+            id,
+            set,
+            ix: IndexSet(len),
+            array_expr,
+            ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntSet(set));
+            if *len == 0 || checker.non_empty()? {
+                for (pos, e) in int_elements(array_expr, symbols).iter().enumerate() {
+                    checker.int_expr(e, Some(pos))?;
+                }
+            }
+        }
+        VarDeclItem::ArrayOfBoundedFloat {
+            id,
+            lb,
+            ub,
+            ix: IndexSet(len),
+            array_expr,
+            ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::FloatRange(*lb, *ub));
+            if *len == 0 || checker.non_empty()? {
+                if let Some(ArrayOfFloatExpr::Array(v)) = array_expr {
+                    for (pos, e) in v.iter().enumerate() {
+                        checker.float_expr(e, Some(pos))?;
+                    }
+                }
+            }
+        }
+        VarDeclItem::ArrayOfSubSetOfIntRange {
+            id,
+            lb,
+            ub,
+            array_expr,
+            ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntRange(*lb, *ub));
+            if let Some(ArrayOfSetExpr::Array(v)) = array_expr {
+                for (pos, e) in v.iter().enumerate() {
+                    checker.set_expr(e, Some(pos))?;
+                }
+            }
+        }
+        VarDeclItem::ArrayOfSubSetOfIntSet {
+            id,
+            set,
+            array_expr,
+            ..
+        } => {
+            let checker = Checker::new(config, symbols, id, Domain::IntSet(set));
+            if let Some(ArrayOfSetExpr::Array(v)) = array_expr {
+                for (pos, e) in v.iter().enumerate() {
+                    checker.set_expr(e, Some(pos))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+/// Returns the elements of an integer array, resolving a parameter array
+fn int_elements(e: &Option<ArrayOfIntExpr>, symbols: &SymbolTable) -> Vec<IntExpr> {
+    match e {
+        Some(ArrayOfIntExpr::Array(v)) => v.clone(),
+        Some(ArrayOfIntExpr::VarParIdentifier(id)) => match symbols.parameter(id) {
+            Some(ParDeclItem::ArrayOfInt { v, .. }) => v.iter().map(|i| IntExpr::Int(*i)).collect(),
+            _ => vec![],
+        },
+        None => vec![],
+    }
+}
+enum Domain<'a> {
+    IntRange(i128, i128),
+    IntSet(&'a [i128]),
+    FloatRange(f64, f64),
+}
+impl<'a> Domain<'a> {
+    fn is_empty(&self) -> bool {
+        match self {
+            Domain::IntRange(lb, ub) => lb > ub,
+            Domain::IntSet(set) => set.is_empty(),
+            Domain::FloatRange(lb, ub) => lb > ub,
+        }
+    }
+    fn contains_int(&self, value: i128) -> bool {
+        match self {
+            Domain::IntRange(lb, ub) => *lb <= value && value <= *ub,
+            Domain::IntSet(set) => set.contains(&value),
+            Domain::FloatRange(lb, ub) => *lb <= value as f64 && value as f64 <= *ub,
+        }
+    }
+    fn contains_float(&self, value: f64) -> bool {
+        match self {
+            Domain::FloatRange(lb, ub) => *lb <= value && value <= *ub,
+            _ => true,
+        }
+    }
+    fn describe(&self) -> String {
+        match self {
+            Domain::IntRange(lb, ub) => format!("{}..{}", lb, ub),
+            Domain::IntSet(set) => format!(
+                "{{{}}}",
+                set.iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Domain::FloatRange(lb, ub) => format!("{:?}..{:?}", lb, ub),
+        }
+    }
+}
+struct Checker<'a> {
+    config: &'a Config,
+    symbols: &'a SymbolTable,
+    id: &'a str,
+    domain: Domain<'a>,
+}
+impl<'a> Checker<'a> {
+    fn new(config: &'a Config, symbols: &'a SymbolTable, id: &'a str, domain: Domain<'a>) -> Self {
+        Checker {
+            config,
+            symbols,
+            id,
+            domain,
+        }
+    }
+    fn location(&self, pos: Option<usize>) -> String {
+        match pos {
+            Some(pos) => format!("variable {} element {}", self.id, pos),
+            None => format!("variable {}", self.id),
+        }
+    }
+    fn report(&self, err: FlatZincError) -> Result<()> {
+        if self.config.lenient {
+            warn!("{}", err);
+            Ok(())
+        } else {
+            Err(err.into())
+        }
+    }
+    /// Reports an empty domain, returns whether the domain is not empty
+    fn non_empty(&self) -> Result<bool> {
+        if self.domain.is_empty() {
+            self.report(FlatZincError::EmptyDomain {
+                location: self.location(None),
+                domain: self.domain.describe(),
+            })?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    fn outside(&self, value: String, pos: Option<usize>) -> Result<()> {
+        self.report(FlatZincError::ValueOutsideDomain {
+            value,
+            location: self.location(pos),
+            domain: self.domain.describe(),
+        })
+    }
+    fn int(&self, value: i128, pos: Option<usize>) -> Result<()> {
+        if self.domain.contains_int(value) {
+            Ok(())
+        } else {
+            self.outside(value.to_string(), pos)
+        }
+    }
+    fn int_expr(&self, e: &IntExpr, pos: Option<usize>) -> Result<()> {
+        match e {
+            IntExpr::Int(i) => self.int(*i, pos),
+            IntExpr::VarParIdentifier(id) => match self.symbols.parameter(id) {
+                Some(ParDeclItem::Int { int, .. }) => self.int(*int, pos),
+                _ => Ok(()),
+            },
+        }
+    }
+    fn opt_int_expr(&self, e: &Option<IntExpr>, pos: Option<usize>) -> Result<()> {
+        match e {
+            Some(e) => self.int_expr(e, pos),
+            None => Ok(()),
+        }
+    }
+    fn float_expr(&self, e: &FloatExpr, pos: Option<usize>) -> Result<()> {
+        let value = match e {
+            FloatExpr::Float(f) => *f,
+            FloatExpr::VarParIdentifier(id) => match self.symbols.parameter(id) {
+                Some(ParDeclItem::Float { float, .. }) => *float,
+                _ => return Ok(()),
+            },
+        };
+        if self.domain.contains_float(value) {
+            Ok(())
+        } else {
+            self.outside(format!("{:?}", value), pos)
+        }
+    }
+    /// Checks that all elements of a set value are contained in the domain
+    fn set_expr(&self, e: &SetExpr, pos: Option<usize>) -> Result<()> {
+        let values = match e {
+            SetExpr::Set(SetLiteralExpr::IntInRange(IntExpr::Int(lb), IntExpr::Int(ub))) => {
+                SetValues::Range(*lb, *ub)
+            }
+            SetExpr::Set(SetLiteralExpr::SetInts(v)) => SetValues::Ints(
+                v.iter()
+                    .filter_map(|e| match e {
+                        IntExpr::Int(i) => Some(*i),
+                        IntExpr::VarParIdentifier(_) => None,
+                    })
+                    .collect(),
+            ),
+            SetExpr::VarParIdentifier(id) => match self.symbols.parameter(id) {
+                Some(ParDeclItem::SetOfInt {
+                    set_literal: SetLiteral::IntRange(lb, ub),
+                    ..
+                }) => SetValues::Range(*lb, *ub),
+                Some(ParDeclItem::SetOfInt {
+                    set_literal: SetLiteral::SetInts(v),
+                    ..
+                }) => SetValues::Ints(v.clone()),
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let outside = match values {
+            SetValues::Range(lb, ub) if lb > ub => None,
+            SetValues::Range(lb, ub) => match self.domain {
+                Domain::IntRange(dlb, dub) if dlb <= lb && ub <= dub => None,
+                Domain::IntRange(dlb, _) if lb < dlb => Some(lb),
+                Domain::IntRange(..) => Some(ub),
+                _ => (lb..=ub).find(|i| !self.domain.contains_int(*i)),
+            },
+            SetValues::Ints(v) => v.into_iter().find(|i| !self.domain.contains_int(*i)),
+        };
+        match outside {
+            Some(value) => self.outside(value.to_string(), pos),
+            None => Ok(()),
+        }
+    }
+}
+enum SetValues {
+    Range(i128, i128),
+    Ints(Vec<i128>),
+}
+/// Values allowed by the domain of a variable, or of the elements of a variable array
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Values {
+    /// No restriction beyond the type
+    Any,
+    IntRange(i128, i128),
+    IntSet(Vec<i128>),
+    FloatRange(f64, f64),
+}
+impl Values {
+    /// Returns true if every value allowed by `other` is allowed by `self`
+    pub(crate) fn contains(&self, other: &Values) -> bool {
+        match (self, other) {
+            (Values::Any, _) => true,
+            (Values::IntRange(l, u), Values::IntRange(a, b)) => l <= a && b <= u,
+            (Values::IntRange(l, u), Values::IntSet(set)) => set.iter().all(|v| l <= v && v <= u),
+            (Values::IntSet(set), Values::IntSet(other)) => other.iter().all(|v| set.contains(v)),
+            (Values::IntSet(set), Values::IntRange(a, b)) => {
+                b.checked_sub(*a).is_some_and(|n| n < set.len() as i128)
+                    && (*a..=*b).all(|v| set.contains(&v))
+            }
+            (Values::FloatRange(l, u), Values::FloatRange(a, b)) => l <= a && b <= u,
+            _ => false,
+        }
+    }
+}
+/// Domain of a declaration, where set variables are described by the domain of their elements
+pub(crate) fn declared_values(item: &VarDeclItem) -> Values {
+    match item {
+        VarDeclItem::IntInRange { lb, ub, .. }
+        | VarDeclItem::SubSetOfIntRange { lb, ub, .. }
+        | VarDeclItem::ArrayOfIntInRange { lb, ub, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntRange { lb, ub, .. } => Values::IntRange(*lb, *ub),
+        VarDeclItem::IntInSet { set, .. }
+        | VarDeclItem::SubSetOfIntSet { set, .. }
+        | VarDeclItem::ArrayOfIntInSet { set, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntSet { set, .. } => Values::IntSet(set.clone()),
+        VarDeclItem::BoundedFloat { lb, ub, .. }
+        | VarDeclItem::ArrayOfBoundedFloat { lb, ub, .. } => Values::FloatRange(*lb, *ub),
+        _ => Values::Any,
+    }
+}
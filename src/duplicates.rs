@@ -0,0 +1,63 @@
+use flatzinc::*;
+use std::collections::hash_map::{Entry, HashMap};
+
+/// Canonical forms of the constraints seen so far, used to detect duplicates
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    first: HashMap<String, usize>,
+}
+impl Duplicates {
+    /// Records constraint `i` and returns the number of the first identical constraint, if any
+    ///
+    /// Constraints are identical if they have the same name and arguments,
+    /// where annotations are ignored and set literals are sorted.
+    pub fn check(&mut self, c: &ConstraintItem, i: usize) -> Option<usize> {
+        match self.first.entry(canonical(c)) {
+            Entry::Occupied(first) => Some(*first.get()),
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+                None
+            }
+        }
+    }
+}
+fn canonical(c: &ConstraintItem) -> String {
+    let exprs: Vec<Expr> = c.exprs.iter().cloned().map(normalize_expr).collect();
+    format!("{}{:?}", c.id, exprs)
+}
+fn normalize_expr(e: Expr) -> Expr {
+    match e {
+        Expr::Set(sl) => Expr::Set(normalize_set_literal(sl)),
+        Expr::ArrayOfSet(v) => Expr::ArrayOfSet(
+            v.into_iter()
+                .map(|e| match e {
+                    SetExpr::Set(sl) => SetExpr::Set(normalize_set_literal(sl)),
+                    e => e,
+                })
+                .collect(),
+        ),
+        e => e,
+    }
+}
+fn normalize_set_literal(sl: SetLiteralExpr) -> SetLiteralExpr {
+    match sl {
+        SetLiteralExpr::SetInts(v) => {
+            let values: Option<Vec<i128>> = v
+                .iter()
+                .map(|e| match e {
+                    IntExpr::Int(i) => Some(*i),
+                    IntExpr::VarParIdentifier(_) => None,
+                })
+                .collect();
+            match values {
+                Some(mut values) => {
+                    values.sort_unstable();
+                    values.dedup();
+                    SetLiteralExpr::SetInts(values.into_iter().map(IntExpr::Int).collect())
+                }
+                None => SetLiteralExpr::SetInts(v),
+            }
+        }
+        sl => sl,
+    }
+}
@@ -0,0 +1,173 @@
+use crate::domain::{declared_values, Values};
+use crate::incidence::{
+    annotation_ids, argument_ids, array_element_ids, assigned_id, float_id, int_id, objective_id,
+    set_id,
+};
+use crate::references::{par_decl_id, var_decl_id_annos};
+use flatzinc::*;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Declaration removed from a model because nothing refers to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedDeclaration {
+    pub id: String,
+    /// Either `parameter` or `variable`
+    pub kind: &'static str,
+    /// Index of the statement in the model
+    pub index: usize,
+}
+
+/// Parameters and introduced variables that no constraint, output annotation,
+/// objective, search annotation or other kept declaration refers to
+///
+/// Introduced variables and arrays whose domain is stricter than the domain
+/// of the variables assigned to them are kept, since they restrict the solutions.
+#[derive(Debug, Default)]
+pub struct Elimination {
+    removed: Vec<RemovedDeclaration>,
+    indices: HashSet<usize>,
+}
+impl Elimination {
+    /// Determines the dead declarations of a model, given the statements
+    /// and the annotations of the parameter declarations
+    pub fn new(stmts: &[FzStmt], par_annos: &[Vec<Annotation>]) -> Self {
+        // identifiers referred to by each declaration
+        let mut declarations: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut live = vec![];
+        // domains of the variables declared so far
+        let mut domains = HashMap::new();
+        for (stmt, par_annos) in stmts.iter().zip(par_annos) {
+            match stmt {
+                FzStmt::Parameter(p) => {
+                    let id = par_decl_id(p);
+                    declarations.insert(id, vec![]);
+                    if is_output(par_annos) {
+                        live.push(id);
+                    }
+                }
+                FzStmt::Variable(d) => {
+                    let (id, annos) = var_decl_id_annos(d);
+                    let mut ids = vec![];
+                    match array_element_ids(d) {
+                        Some((_, elements)) => ids.extend(elements),
+                        None => ids.extend(assigned_id(d)),
+                    }
+                    annotation_ids(annos, &mut ids);
+                    declarations.insert(id, ids);
+                    domains.insert(id, declared_values(d));
+                    if is_output(annos)
+                        || !annos.iter().any(|a| a.id == "var_is_introduced")
+                        || restricts_assignment(d, &domains)
+                    {
+                        live.push(id);
+                    }
+                }
+                FzStmt::Constraint(c) => {
+                    for e in &c.exprs {
+                        argument_ids(e, &mut live);
+                    }
+                }
+                FzStmt::SolveItem(i) => {
+                    live.extend(objective_id(&i.goal));
+                    annotation_ids(&i.annotations, &mut live);
+                }
+                FzStmt::Comment(_) | FzStmt::Predicate(_) => {}
+            }
+        }
+        let mut reached = HashSet::new();
+        while let Some(id) = live.pop() {
+            if reached.insert(id) {
+                if let Some(ids) = declarations.get(id) {
+                    live.extend(ids.iter().copied());
+                }
+            }
+        }
+        let mut elimination = Elimination::default();
+        for (index, stmt) in stmts.iter().enumerate() {
+            let (id, kind) = match stmt {
+                FzStmt::Parameter(p) => (par_decl_id(p), "parameter"),
+                FzStmt::Variable(d) => (var_decl_id_annos(d).0, "variable"),
+                _ => continue,
+            };
+            if reached.contains(id) {
+                continue;
+            }
+            elimination.indices.insert(index);
+            elimination.removed.push(RemovedDeclaration {
+                id: id.to_string(),
+                kind,
+                index,
+            });
+        }
+        elimination
+    }
+    /// Returns true if the statement with the given index is removed
+    pub fn removes(&self, index: usize) -> bool {
+        self.indices.contains(&index)
+    }
+    /// Returns the removed declarations in the order of the model
+    pub fn removed(&self) -> &[RemovedDeclaration] {
+        &self.removed
+    }
+    pub fn write_report(&self, mut out: impl Write) -> io::Result<()> {
+        for r in &self.removed {
+            writeln!(
+                out,
+                "Removed unused {} {} in line {}",
+                r.kind,
+                r.id,
+                r.index + 1
+            )?;
+        }
+        Ok(())
+    }
+}
+fn is_output(annos: &[Annotation]) -> bool {
+    annos
+        .iter()
+        .any(|a| a.id == "output_var" || a.id == "output_array")
+}
+
+/// Returns true if the declaration is assigned a literal,
+/// or a variable whose domain is not contained in the declared domain
+fn restricts_assignment(item: &VarDeclItem, domains: &HashMap<&str, Values>) -> bool {
+    let values = declared_values(item);
+    if values == Values::Any {
+        return false;
+    }
+    match assigned_ids(item) {
+        Some(ids) => !ids.iter().all(|id| {
+            id.and_then(|id| domains.get(id))
+                .is_some_and(|other| values.contains(other))
+        }),
+        None => false,
+    }
+}
+/// Returns the identifiers assigned to a declaration, with None for literals,
+/// or None if the declaration is not assigned
+fn assigned_ids(item: &VarDeclItem) -> Option<Vec<Option<&str>>> {
+    let ids = match item {
+        VarDeclItem::IntInRange { expr: Some(e), .. }
+        | VarDeclItem::IntInSet { expr: Some(e), .. } => vec![int_id(e)],
+        VarDeclItem::BoundedFloat { expr: Some(e), .. } => vec![float_id(e)],
+        VarDeclItem::SubSetOfIntRange { expr: Some(e), .. }
+        | VarDeclItem::SubSetOfIntSet { expr: Some(e), .. } => vec![set_id(e)],
+        VarDeclItem::ArrayOfIntInRange { array_expr, .. }
+        | VarDeclItem::ArrayOfIntInSet { array_expr, .. } => match array_expr.as_ref()? {
+            ArrayOfIntExpr::Array(v) => v.iter().map(int_id).collect(),
+            ArrayOfIntExpr::VarParIdentifier(id) => vec![Some(id.as_str())],
+        },
+        VarDeclItem::ArrayOfBoundedFloat { array_expr, .. } => match array_expr.as_ref()? {
+            ArrayOfFloatExpr::Array(v) => v.iter().map(float_id).collect(),
+            ArrayOfFloatExpr::VarParIdentifier(id) => vec![Some(id.as_str())],
+        },
+        VarDeclItem::ArrayOfSubSetOfIntRange { array_expr, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntSet { array_expr, .. } => match array_expr.as_ref()? {
+            ArrayOfSetExpr::Array(v) => v.iter().map(set_id).collect(),
+            ArrayOfSetExpr::VarParIdentifier(id) => vec![Some(id.as_str())],
+        },
+        _ => return None,
+    };
+    Some(ids)
+}
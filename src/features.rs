@@ -0,0 +1,229 @@
+use crate::incidence::Incidence;
+use crate::json;
+use flatzinc::*;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Numeric features of a model for algorithm selection, collected in a single pass
+#[derive(Debug, Default)]
+pub struct Features {
+    incidence: Incidence,
+    parameters: usize,
+    bool_variables: usize,
+    int_variables: usize,
+    float_variables: usize,
+    set_variables: usize,
+    variable_arrays: usize,
+    /// Domain sizes of the integer variables with a finite domain
+    domain_sizes: HashMap<String, f64>,
+    constraints: usize,
+    int_constraints: usize,
+    bool_constraints: usize,
+    float_constraints: usize,
+    set_constraints: usize,
+    global_constraints: usize,
+    linear_constraints: usize,
+    reified_constraints: usize,
+    /// Number of constraints each scalar variable occurs in
+    variable_degrees: HashMap<String, usize>,
+    /// Number of distinct variables of each constraint
+    constraint_degrees: Vec<usize>,
+    /// 0 for satisfaction, 1 for minimization and 2 for maximization problems
+    objective: usize,
+    objective_variable: Option<String>,
+    search_annotations: usize,
+}
+impl Features {
+    pub fn add(&mut self, stmt: &FzStmt) {
+        match stmt {
+            FzStmt::Comment(_) | FzStmt::Predicate(_) => {}
+            FzStmt::Parameter(_) => self.parameters += 1,
+            FzStmt::Variable(d) => {
+                self.incidence.declare(d);
+                let id = match d {
+                    VarDeclItem::Bool { id, .. } => {
+                        self.bool_variables += 1;
+                        id
+                    }
+                    VarDeclItem::Int { id, .. } => {
+                        self.int_variables += 1;
+                        id
+                    }
+                    VarDeclItem::IntInRange { id, lb, ub, .. } => {
+                        self.int_variables += 1;
+                        let size = if ub < lb {
+                            0.0
+                        } else {
+                            ub.abs_diff(*lb) as f64 + 1.0
+                        };
+                        self.domain_sizes.insert(id.clone(), size);
+                        id
+                    }
+                    VarDeclItem::IntInSet { id, set, .. } => {
+                        self.int_variables += 1;
+                        let mut set = set.clone();
+                        set.sort_unstable();
+                        set.dedup();
+                        self.domain_sizes.insert(id.clone(), set.len() as f64);
+                        id
+                    }
+                    VarDeclItem::Float { id, .. } | VarDeclItem::BoundedFloat { id, .. } => {
+                        self.float_variables += 1;
+                        id
+                    }
+                    VarDeclItem::SetOfInt { id, .. }
+                    | VarDeclItem::SubSetOfIntRange { id, .. }
+                    | VarDeclItem::SubSetOfIntSet { id, .. } => {
+                        self.set_variables += 1;
+                        id
+                    }
+                    _ => {
+                        self.variable_arrays += 1;
+                        return;
+                    }
+                };
+                self.variable_degrees.insert(id.clone(), 0);
+            }
+            FzStmt::Constraint(c) => {
+                self.constraints += 1;
+                let name = c.id.trim_start_matches("array_").trim_start_matches("var_");
+                if name.starts_with("int") {
+                    self.int_constraints += 1;
+                } else if name.starts_with("bool") {
+                    self.bool_constraints += 1;
+                } else if name.starts_with("float") {
+                    self.float_constraints += 1;
+                } else if name.starts_with("set") {
+                    self.set_constraints += 1;
+                } else {
+                    self.global_constraints += 1;
+                }
+                if c.id.contains("_lin_") {
+                    self.linear_constraints += 1;
+                }
+                if c.id.ends_with("_reif") {
+                    self.reified_constraints += 1;
+                }
+                let variables = self.incidence.constraint_variables(c);
+                self.constraint_degrees.push(variables.len());
+                for id in variables {
+                    if let Some(degree) = self.variable_degrees.get_mut(id) {
+                        *degree += 1;
+                    }
+                }
+            }
+            FzStmt::SolveItem(i) => {
+                self.search_annotations = i.annotations.len();
+                let (objective, id) = match &i.goal {
+                    Goal::Satisfy => (0, None),
+                    Goal::OptimizeBool(t, BoolExpr::VarParIdentifier(id))
+                    | Goal::OptimizeInt(t, IntExpr::VarParIdentifier(id))
+                    | Goal::OptimizeFloat(t, FloatExpr::VarParIdentifier(id))
+                    | Goal::OptimizeSet(t, SetExpr::VarParIdentifier(id)) => {
+                        (opt_type(t), Some(id.clone()))
+                    }
+                    Goal::OptimizeBool(t, _)
+                    | Goal::OptimizeInt(t, _)
+                    | Goal::OptimizeFloat(t, _)
+                    | Goal::OptimizeSet(t, _) => (opt_type(t), None),
+                };
+                self.objective = objective;
+                self.objective_variable = id;
+            }
+        }
+    }
+    /// Returns the names and values of all features
+    pub fn values(&self) -> Vec<(&'static str, f64)> {
+        let ratio = |n: usize| {
+            if self.constraints == 0 {
+                0.0
+            } else {
+                n as f64 / self.constraints as f64
+            }
+        };
+        let domain = summary(self.domain_sizes.values().copied());
+        let variable_degree = summary(self.variable_degrees.values().map(|d| *d as f64));
+        let constraint_degree = summary(self.constraint_degrees.iter().map(|d| *d as f64));
+        let objective_variable = self.objective_variable.as_deref();
+        vec![
+            ("parameters", self.parameters as f64),
+            (
+                "variables",
+                (self.bool_variables
+                    + self.int_variables
+                    + self.float_variables
+                    + self.set_variables) as f64,
+            ),
+            ("bool_variables", self.bool_variables as f64),
+            ("int_variables", self.int_variables as f64),
+            ("float_variables", self.float_variables as f64),
+            ("set_variables", self.set_variables as f64),
+            ("variable_arrays", self.variable_arrays as f64),
+            ("constraints", self.constraints as f64),
+            ("domain_size_min", domain[0]),
+            ("domain_size_max", domain[1]),
+            ("domain_size_mean", domain[2]),
+            ("domain_size_std", domain[3]),
+            ("int_constraint_ratio", ratio(self.int_constraints)),
+            ("bool_constraint_ratio", ratio(self.bool_constraints)),
+            ("float_constraint_ratio", ratio(self.float_constraints)),
+            ("set_constraint_ratio", ratio(self.set_constraints)),
+            ("global_constraint_ratio", ratio(self.global_constraints)),
+            ("linear_constraint_ratio", ratio(self.linear_constraints)),
+            ("reified_constraint_ratio", ratio(self.reified_constraints)),
+            ("variable_degree_min", variable_degree[0]),
+            ("variable_degree_max", variable_degree[1]),
+            ("variable_degree_mean", variable_degree[2]),
+            ("variable_degree_std", variable_degree[3]),
+            ("constraint_degree_min", constraint_degree[0]),
+            ("constraint_degree_max", constraint_degree[1]),
+            ("constraint_degree_mean", constraint_degree[2]),
+            ("constraint_degree_std", constraint_degree[3]),
+            ("objective", self.objective as f64),
+            (
+                "objective_degree",
+                objective_variable
+                    .and_then(|id| self.variable_degrees.get(id))
+                    .map_or(0.0, |d| *d as f64),
+            ),
+            (
+                "objective_domain_size",
+                objective_variable
+                    .and_then(|id| self.domain_sizes.get(id))
+                    .copied()
+                    .unwrap_or(0.0),
+            ),
+            ("search_annotations", self.search_annotations as f64),
+        ]
+    }
+    /// Writes a header line with the feature names and a line with the values
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        let values = self.values();
+        let names: Vec<&str> = values.iter().map(|(name, _)| *name).collect();
+        let values: Vec<String> = values.iter().map(|(_, v)| v.to_string()).collect();
+        writeln!(out, "{}", names.join(","))?;
+        writeln!(out, "{}", values.join(","))
+    }
+    pub fn write_json(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{}", json::object(self.values()))
+    }
+}
+fn opt_type(t: &OptimizationType) -> usize {
+    match t {
+        OptimizationType::Minimize => 1,
+        OptimizationType::Maximize => 2,
+    }
+}
+/// Returns the minimum, maximum, mean and standard deviation, or zeros if there are no values
+fn summary(values: impl Iterator<Item = f64>) -> [f64; 4] {
+    let values: Vec<f64> = values.collect();
+    if values.is_empty() {
+        return [0.0; 4];
+    }
+    let n = values.len() as f64;
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n;
+    [min, max, mean, variance.sqrt()]
+}
@@ -0,0 +1,127 @@
+use crate::incidence::{scalar_id, Incidence};
+use flatzinc::FzStmt;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Bipartite graph linking every constraint to the variables it references
+#[derive(Debug, Default)]
+pub struct IncidenceGraph {
+    constraint_counter: usize,
+    incidence: Incidence,
+    /// Scalar variables in the order of their declaration
+    variables: Vec<String>,
+    /// Number, name and variables of every constraint
+    constraints: Vec<(usize, String, Vec<String>)>,
+}
+impl IncidenceGraph {
+    /// Creates an empty graph, the constraints are numbered like in the conversion,
+    /// where the counter is incremented before each constraint
+    pub fn new(constraint_counter: usize) -> Self {
+        IncidenceGraph {
+            constraint_counter,
+            ..Default::default()
+        }
+    }
+    pub fn add(&mut self, stmt: &FzStmt) {
+        match stmt {
+            FzStmt::Variable(d) => {
+                self.incidence.declare(d);
+                if let Some(id) = scalar_id(d) {
+                    self.variables.push(id.to_string());
+                }
+            }
+            FzStmt::Constraint(c) => {
+                self.constraint_counter += 1;
+                let variables = self
+                    .incidence
+                    .constraint_variables(c)
+                    .into_iter()
+                    .map(|id| id.to_string())
+                    .collect();
+                self.constraints
+                    .push((self.constraint_counter, c.id.clone(), variables));
+            }
+            _ => {}
+        }
+    }
+    /// Returns the node ids of the variables, `v0` for the first declared variable and so on
+    fn variable_nodes(&self) -> HashMap<&str, String> {
+        self.variables
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), format!("v{}", i)))
+            .collect()
+    }
+    pub fn write_dot(&self, mut out: impl Write) -> io::Result<()> {
+        let nodes = self.variable_nodes();
+        writeln!(out, "graph incidence {{")?;
+        writeln!(out, "  node [shape=box];")?;
+        for (i, name, _) in &self.constraints {
+            writeln!(out, "  c{} [label={}];", i, dot_string(name))?;
+        }
+        writeln!(out, "  node [shape=ellipse];")?;
+        for id in &self.variables {
+            writeln!(out, "  {} [label={}];", nodes[id.as_str()], dot_string(id))?;
+        }
+        for (i, _, variables) in &self.constraints {
+            for id in variables {
+                writeln!(out, "  c{} -- {};", i, nodes[id.as_str()])?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+    pub fn write_graphml(&self, mut out: impl Write) -> io::Result<()> {
+        let nodes = self.variable_nodes();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="kind" for="node" attr.name="kind" attr.type="string"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+        )?;
+        writeln!(out, r#"  <graph id="incidence" edgedefault="undirected">"#)?;
+        for (i, name, _) in &self.constraints {
+            write_graphml_node(&mut out, &format!("c{}", i), "constraint", name)?;
+        }
+        for id in &self.variables {
+            write_graphml_node(&mut out, &nodes[id.as_str()], "variable", id)?;
+        }
+        for (i, _, variables) in &self.constraints {
+            for id in variables {
+                writeln!(
+                    out,
+                    r#"    <edge source="c{}" target="{}"/>"#,
+                    i,
+                    nodes[id.as_str()]
+                )?;
+            }
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+}
+fn write_graphml_node(mut out: impl Write, node: &str, kind: &str, label: &str) -> io::Result<()> {
+    writeln!(out, r#"    <node id="{}">"#, node)?;
+    writeln!(out, r#"      <data key="kind">{}</data>"#, kind)?;
+    writeln!(
+        out,
+        r#"      <data key="label">{}</data>"#,
+        xml_string(label)
+    )?;
+    writeln!(out, "    </node>")
+}
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+fn xml_string(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
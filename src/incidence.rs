@@ -0,0 +1,247 @@
+use flatzinc::*;
+use std::collections::{HashMap, HashSet};
+
+/// Declared variables of a model, used to determine the variables of a constraint
+///
+/// References to variable arrays are resolved to the variables among their elements.
+#[derive(Debug, Default)]
+pub(crate) struct Incidence {
+    variables: HashSet<String>,
+    arrays: HashMap<String, Vec<String>>,
+}
+impl Incidence {
+    /// Records a variable declaration
+    pub(crate) fn declare(&mut self, item: &VarDeclItem) {
+        if let Some(id) = scalar_id(item) {
+            self.variables.insert(id.to_string());
+        } else if let Some((id, elements)) = array_element_ids(item) {
+            let elements = elements
+                .into_iter()
+                .filter(|e| self.variables.contains(*e))
+                .map(|e| e.to_string())
+                .collect();
+            self.arrays.insert(id.to_string(), elements);
+        }
+    }
+    /// Returns the distinct variables occurring in the arguments of a constraint
+    pub(crate) fn constraint_variables<'a>(&'a self, c: &'a ConstraintItem) -> Vec<&'a str> {
+        let mut ids = vec![];
+        for e in &c.exprs {
+            match e {
+                Expr::VarParIdentifier(id) if self.arrays.contains_key(id) => {
+                    ids.extend(self.arrays[id].iter().map(|e| e.as_str()))
+                }
+                e => argument_ids(e, &mut ids),
+            }
+        }
+        let mut seen = HashSet::new();
+        ids.retain(|id| self.variables.contains(*id) && seen.insert(*id));
+        ids
+    }
+}
+/// Collects the identifiers occurring in a constraint argument, without resolving arrays
+pub(crate) fn argument_ids<'a>(e: &'a Expr, ids: &mut Vec<&'a str>) {
+    match e {
+        Expr::VarParIdentifier(id) => ids.push(id),
+        Expr::Bool(_) | Expr::Int(_) | Expr::Float(_) => {}
+        Expr::Set(sl) => set_literal_ids(sl, ids),
+        Expr::ArrayOfBool(v) => ids.extend(v.iter().filter_map(bool_id)),
+        Expr::ArrayOfInt(v) => ids.extend(v.iter().filter_map(int_id)),
+        Expr::ArrayOfFloat(v) => ids.extend(v.iter().filter_map(float_id)),
+        Expr::ArrayOfSet(v) => {
+            for e in v {
+                match e {
+                    SetExpr::VarParIdentifier(id) => ids.push(id),
+                    SetExpr::Set(sl) => set_literal_ids(sl, ids),
+                }
+            }
+        }
+    }
+}
+/// Returns the identifier of a variable array declaration and the identifiers among its elements
+pub(crate) fn array_element_ids(item: &VarDeclItem) -> Option<(&str, Vec<&str>)> {
+    let (id, elements) = match item {
+        VarDeclItem::ArrayOfBool { id, array_expr, .. } => match array_expr {
+            Some(ArrayOfBoolExpr::Array(v)) => (id, v.iter().filter_map(bool_id).collect()),
+            _ => (id, vec![]),
+        },
+        VarDeclItem::ArrayOfInt { id, array_expr, .. }
+        | VarDeclItem::ArrayOfIntInRange { id, array_expr, .. }
+        | VarDeclItem::ArrayOfIntInSet { id, array_expr, .. } => match array_expr {
+            Some(ArrayOfIntExpr::Array(v)) => (id, v.iter().filter_map(int_id).collect()),
+            _ => (id, vec![]),
+        },
+        VarDeclItem::ArrayOfFloat { id, array_expr, .. }
+        | VarDeclItem::ArrayOfBoundedFloat { id, array_expr, .. } => match array_expr {
+            Some(ArrayOfFloatExpr::Array(v)) => (id, v.iter().filter_map(float_id).collect()),
+            _ => (id, vec![]),
+        },
+        VarDeclItem::ArrayOfSet { id, array_expr, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntRange { id, array_expr, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntSet { id, array_expr, .. } => match array_expr {
+            Some(ArrayOfSetExpr::Array(v)) => (id, v.iter().filter_map(set_id).collect()),
+            _ => (id, vec![]),
+        },
+        _ => return None,
+    };
+    Some((id, elements))
+}
+/// Returns the identifier of a scalar variable declaration
+pub(crate) fn scalar_id(item: &VarDeclItem) -> Option<&str> {
+    match item {
+        VarDeclItem::Bool { id, .. }
+        | VarDeclItem::Int { id, .. }
+        | VarDeclItem::IntInRange { id, .. }
+        | VarDeclItem::IntInSet { id, .. }
+        | VarDeclItem::Float { id, .. }
+        | VarDeclItem::BoundedFloat { id, .. }
+        | VarDeclItem::SetOfInt { id, .. }
+        | VarDeclItem::SubSetOfIntRange { id, .. }
+        | VarDeclItem::SubSetOfIntSet { id, .. } => Some(id),
+        _ => None,
+    }
+}
+/// Returns the identifier of the objective of a solve item, like `x` in `solve minimize x;`
+pub(crate) fn objective_id(goal: &Goal) -> Option<&str> {
+    match goal {
+        Goal::OptimizeBool(_, e) => bool_id(e),
+        Goal::OptimizeInt(_, e) => int_id(e),
+        Goal::OptimizeFloat(_, e) => float_id(e),
+        Goal::OptimizeSet(_, e) => set_id(e),
+        Goal::Satisfy => None,
+    }
+}
+/// Collects the identifiers occurring in the arguments of annotations
+pub(crate) fn annotation_ids<'a>(annos: &'a [Annotation], ids: &mut Vec<&'a str>) {
+    for a in annos {
+        for e in &a.expressions {
+            match e {
+                AnnExpr::Annotations(annos) => annotation_ids(annos, ids),
+                AnnExpr::String(_) => {}
+                AnnExpr::Expr(e) => argument_ids(e, ids),
+            }
+        }
+    }
+}
+/// Returns the identifier assigned to a scalar variable declaration, like `y` in `var int: x = y;`
+pub(crate) fn assigned_id(item: &VarDeclItem) -> Option<&str> {
+    match item {
+        VarDeclItem::Bool { expr: Some(e), .. } => bool_id(e),
+        VarDeclItem::Int { expr: Some(e), .. }
+        | VarDeclItem::IntInRange { expr: Some(e), .. }
+        | VarDeclItem::IntInSet { expr: Some(e), .. } => int_id(e),
+        VarDeclItem::Float { expr: Some(e), .. }
+        | VarDeclItem::BoundedFloat { expr: Some(e), .. } => float_id(e),
+        VarDeclItem::SetOfInt { expr: Some(e), .. }
+        | VarDeclItem::SubSetOfIntRange { expr: Some(e), .. }
+        | VarDeclItem::SubSetOfIntSet { expr: Some(e), .. } => set_id(e),
+        _ => None,
+    }
+}
+fn bool_id(e: &BoolExpr) -> Option<&str> {
+    match e {
+        BoolExpr::VarParIdentifier(id) => Some(id),
+        BoolExpr::Bool(_) => None,
+    }
+}
+pub(crate) fn int_id(e: &IntExpr) -> Option<&str> {
+    match e {
+        IntExpr::VarParIdentifier(id) => Some(id),
+        IntExpr::Int(_) => None,
+    }
+}
+pub(crate) fn float_id(e: &FloatExpr) -> Option<&str> {
+    match e {
+        FloatExpr::VarParIdentifier(id) => Some(id),
+        FloatExpr::Float(_) => None,
+    }
+}
+pub(crate) fn set_id(e: &SetExpr) -> Option<&str> {
+    match e {
+        SetExpr::VarParIdentifier(id) => Some(id),
+        SetExpr::Set(_) => None,
+    }
+}
+fn set_literal_ids<'a>(sl: &'a SetLiteralExpr, ids: &mut Vec<&'a str>) {
+    match sl {
+        SetLiteralExpr::IntInRange(lb, ub) => ids.extend(int_id(lb).into_iter().chain(int_id(ub))),
+        SetLiteralExpr::BoundedFloat(lb, ub) => {
+            ids.extend(float_id(lb).into_iter().chain(float_id(ub)))
+        }
+        SetLiteralExpr::SetInts(v) => ids.extend(v.iter().filter_map(int_id)),
+        SetLiteralExpr::SetFloats(v) => ids.extend(v.iter().filter_map(float_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_fz_stmt;
+
+    fn incidence(stmts: &[&str]) -> Incidence {
+        let mut incidence = Incidence::default();
+        for stmt in stmts {
+            if let FzStmt::Variable(d) = parse_fz_stmt(stmt).unwrap().0 {
+                incidence.declare(&d);
+            }
+        }
+        incidence
+    }
+    fn constraint(stmt: &str) -> ConstraintItem {
+        match parse_fz_stmt(stmt).unwrap().0 {
+            FzStmt::Constraint(c) => c,
+            _ => panic!("not a constraint: {}", stmt),
+        }
+    }
+    fn variable(stmt: &str) -> VarDeclItem {
+        match parse_fz_stmt(stmt).unwrap().0 {
+            FzStmt::Variable(d) => d,
+            _ => panic!("not a variable: {}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_constraint_variables() {
+        let incidence = incidence(&[
+            "int: n = 3;",
+            "var 1..3: x;",
+            "var 1..3: y;",
+            "var 1..3: z;",
+            "array [1..3] of var int: xs = [x,y,2];",
+        ]);
+        let c = constraint("constraint int_lin_le([1,n],[x,n],n);");
+        assert_eq!(incidence.constraint_variables(&c), vec!["x"]);
+        // arrays are resolved to their variables, every variable is reported once
+        let c = constraint("constraint int_lin_eq([1,1,1],xs,z);");
+        assert_eq!(incidence.constraint_variables(&c), vec!["x", "y", "z"]);
+        let c = constraint("constraint int_lin_eq([1,1],[y,y],x);");
+        assert_eq!(incidence.constraint_variables(&c), vec!["y", "x"]);
+        let c = constraint("constraint int_le(u,z);");
+        assert_eq!(incidence.constraint_variables(&c), vec!["z"]);
+    }
+    #[test]
+    fn test_declaration_ids() {
+        assert_eq!(scalar_id(&variable("var bool: b;")), Some("b"));
+        assert_eq!(scalar_id(&variable("array [1..1] of var int: xs;")), None);
+        assert_eq!(
+            array_element_ids(&variable("array [1..3] of var int: xs = [x,1,y];")),
+            Some(("xs", vec!["x", "y"]))
+        );
+        assert_eq!(array_element_ids(&variable("var int: x;")), None);
+        assert_eq!(assigned_id(&variable("var int: x = y;")), Some("y"));
+        assert_eq!(assigned_id(&variable("var int: x = 1;")), None);
+    }
+    #[test]
+    fn test_objective_and_annotation_ids() {
+        let goal = match parse_fz_stmt("solve minimize x;").unwrap().0 {
+            FzStmt::SolveItem(i) => i.goal,
+            _ => unreachable!(),
+        };
+        assert_eq!(objective_id(&goal), Some("x"));
+        assert_eq!(objective_id(&Goal::Satisfy), None);
+        let c = constraint("constraint int_le(x,y) :: foo(y,[z]);");
+        let mut ids = vec![];
+        annotation_ids(&c.annos, &mut ids);
+        assert_eq!(ids, vec!["y", "z"]);
+    }
+}
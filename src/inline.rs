@@ -0,0 +1,404 @@
+use crate::symbols::{BaseType, Symbol, SymbolTable};
+use flatzinc::*;
+
+/// Replaces references to parameters in the arguments of a constraint by their values
+pub(crate) fn inline_constraint(c: &mut ConstraintItem, symbols: &SymbolTable) {
+    for e in &mut c.exprs {
+        expr(e, symbols);
+    }
+}
+/// Replaces references to variable arrays in the arguments of a constraint by their elements
+pub(crate) fn expand_constraint(c: &mut ConstraintItem, symbols: &SymbolTable) {
+    for e in &mut c.exprs {
+        if let Expr::VarParIdentifier(id) = e {
+            if let Some(elements) = symbols.variable_array(id) {
+                *e = elements.clone();
+            }
+        }
+    }
+}
+/// Replaces references to parameters in the assignment of a variable by their values
+pub(crate) fn inline_var_decl_item(item: &mut VarDeclItem, symbols: &SymbolTable) {
+    match item {
+        VarDeclItem::Bool { expr: Some(e), .. } => bool_expr(e, symbols),
+        VarDeclItem::Int { expr: Some(e), .. }
+        | VarDeclItem::IntInRange { expr: Some(e), .. }
+        | VarDeclItem::IntInSet { expr: Some(e), .. } => int_expr(e, symbols),
+        VarDeclItem::Float { expr: Some(e), .. }
+        | VarDeclItem::BoundedFloat { expr: Some(e), .. } => float_expr(e, symbols),
+        VarDeclItem::SetOfInt { expr: Some(e), .. }
+        | VarDeclItem::SubSetOfIntRange { expr: Some(e), .. }
+        | VarDeclItem::SubSetOfIntSet { expr: Some(e), .. } => set_expr(e, symbols),
+        VarDeclItem::ArrayOfBool {
+            array_expr: Some(e),
+            ..
+        } => {
+            if let ArrayOfBoolExpr::VarParIdentifier(id) = e {
+                if let Some(ParDeclItem::ArrayOfBool { v, .. }) = symbols.parameter(id) {
+                    *e = ArrayOfBoolExpr::Array(v.iter().map(|b| BoolExpr::Bool(*b)).collect());
+                }
+            }
+            if let ArrayOfBoolExpr::Array(v) = e {
+                v.iter_mut().for_each(|e| bool_expr(e, symbols));
+            }
+        }
+        VarDeclItem::ArrayOfInt {
+            array_expr: Some(e),
+            ..
+        }
+        | VarDeclItem::ArrayOfIntInRange {
+            array_expr: Some(e),
+            ..
+        }
+        | VarDeclItem::ArrayOfIntInSet {
+            array_expr: Some(e),
+            ..
+        } => {
+            if let ArrayOfIntExpr::VarParIdentifier(id) = e {
+                if let Some(ParDeclItem::ArrayOfInt { v, .. }) = symbols.parameter(id) {
+                    *e = ArrayOfIntExpr::Array(v.iter().map(|i| IntExpr::Int(*i)).collect());
+                }
+            }
+            if let ArrayOfIntExpr::Array(v) = e {
+                v.iter_mut().for_each(|e| int_expr(e, symbols));
+            }
+        }
+        VarDeclItem::ArrayOfFloat {
+            array_expr: Some(e),
+            ..
+        }
+        | VarDeclItem::ArrayOfBoundedFloat {
+            array_expr: Some(e),
+            ..
+        } => {
+            if let ArrayOfFloatExpr::VarParIdentifier(id) = e {
+                if let Some(ParDeclItem::ArrayOfFloat { v, .. }) = symbols.parameter(id) {
+                    *e = ArrayOfFloatExpr::Array(v.iter().map(|f| FloatExpr::Float(*f)).collect());
+                }
+            }
+            if let ArrayOfFloatExpr::Array(v) = e {
+                v.iter_mut().for_each(|e| float_expr(e, symbols));
+            }
+        }
+        VarDeclItem::ArrayOfSet {
+            array_expr: Some(e),
+            ..
+        }
+        | VarDeclItem::ArrayOfSubSetOfIntRange {
+            array_expr: Some(e),
+            ..
+        }
+        | VarDeclItem::ArrayOfSubSetOfIntSet {
+            array_expr: Some(e),
+            ..
+        } => {
+            if let ArrayOfSetExpr::VarParIdentifier(id) = e {
+                if let Some(ParDeclItem::ArrayOfSet { v, .. }) = symbols.parameter(id) {
+                    *e = ArrayOfSetExpr::Array(
+                        v.iter()
+                            .map(|sl| SetExpr::Set(set_literal_expr(sl)))
+                            .collect(),
+                    );
+                }
+            }
+            if let ArrayOfSetExpr::Array(v) = e {
+                v.iter_mut().for_each(|e| set_expr(e, symbols));
+            }
+        }
+        _ => {}
+    }
+}
+fn expr(e: &mut Expr, symbols: &SymbolTable) {
+    if let Expr::VarParIdentifier(id) = e {
+        if let Some(p) = symbols.parameter(id) {
+            *e = par_expr(p);
+        }
+        return;
+    }
+    if let Expr::ArrayOfBool(v) = e {
+        // a list of identifiers is parsed as array of bool,
+        // so the element type is derived from the declarations
+        if let Some(converted) = convert_identifier_array(v, symbols) {
+            *e = converted;
+        }
+    }
+    match e {
+        Expr::Set(sl) => set_literal_expr_inline(sl, symbols),
+        Expr::ArrayOfBool(v) => v.iter_mut().for_each(|e| bool_expr(e, symbols)),
+        Expr::ArrayOfInt(v) => v.iter_mut().for_each(|e| int_expr(e, symbols)),
+        Expr::ArrayOfFloat(v) => v.iter_mut().for_each(|e| float_expr(e, symbols)),
+        Expr::ArrayOfSet(v) => v.iter_mut().for_each(|e| set_expr(e, symbols)),
+        _ => {}
+    }
+}
+/// Converts an array of bool, that only consists of identifiers of another type,
+/// into an array of that type
+fn convert_identifier_array(v: &[BoolExpr], symbols: &SymbolTable) -> Option<Expr> {
+    let mut ids = vec![];
+    let mut base_type = None;
+    for e in v {
+        match e {
+            BoolExpr::VarParIdentifier(id) => {
+                if let Some(Symbol {
+                    base_type: t,
+                    len: None,
+                    ..
+                }) = symbols.get(id)
+                {
+                    base_type.get_or_insert(*t);
+                }
+                ids.push(id.clone());
+            }
+            BoolExpr::Bool(_) => return None,
+        }
+    }
+    match base_type? {
+        BaseType::Bool => None,
+        BaseType::Int => Some(Expr::ArrayOfInt(
+            ids.into_iter().map(IntExpr::VarParIdentifier).collect(),
+        )),
+        BaseType::Float => Some(Expr::ArrayOfFloat(
+            ids.into_iter().map(FloatExpr::VarParIdentifier).collect(),
+        )),
+        BaseType::SetOfInt => Some(Expr::ArrayOfSet(
+            ids.into_iter().map(SetExpr::VarParIdentifier).collect(),
+        )),
+    }
+}
+fn bool_expr(e: &mut BoolExpr, symbols: &SymbolTable) {
+    if let BoolExpr::VarParIdentifier(id) = e {
+        if let Some(ParDeclItem::Bool { bool, .. }) = symbols.parameter(id) {
+            *e = BoolExpr::Bool(*bool);
+        }
+    }
+}
+fn int_expr(e: &mut IntExpr, symbols: &SymbolTable) {
+    if let IntExpr::VarParIdentifier(id) = e {
+        if let Some(ParDeclItem::Int { int, .. }) = symbols.parameter(id) {
+            *e = IntExpr::Int(*int);
+        }
+    }
+}
+fn float_expr(e: &mut FloatExpr, symbols: &SymbolTable) {
+    if let FloatExpr::VarParIdentifier(id) = e {
+        if let Some(ParDeclItem::Float { float, .. }) = symbols.parameter(id) {
+            *e = FloatExpr::Float(*float);
+        }
+    }
+}
+fn set_expr(e: &mut SetExpr, symbols: &SymbolTable) {
+    match e {
+        SetExpr::VarParIdentifier(id) => {
+            if let Some(ParDeclItem::SetOfInt { set_literal, .. }) = symbols.parameter(id) {
+                *e = SetExpr::Set(set_literal_expr(set_literal));
+            }
+        }
+        SetExpr::Set(sl) => set_literal_expr_inline(sl, symbols),
+    }
+}
+fn set_literal_expr_inline(sl: &mut SetLiteralExpr, symbols: &SymbolTable) {
+    match sl {
+        SetLiteralExpr::IntInRange(lb, ub) => {
+            int_expr(lb, symbols);
+            int_expr(ub, symbols);
+        }
+        SetLiteralExpr::BoundedFloat(lb, ub) => {
+            float_expr(lb, symbols);
+            float_expr(ub, symbols);
+        }
+        SetLiteralExpr::SetInts(v) => v.iter_mut().for_each(|e| int_expr(e, symbols)),
+        SetLiteralExpr::SetFloats(v) => v.iter_mut().for_each(|e| float_expr(e, symbols)),
+    }
+}
+fn par_expr(p: &ParDeclItem) -> Expr {
+    match p {
+        ParDeclItem::Bool { bool, .. } => Expr::Bool(*bool),
+        ParDeclItem::Int { int, .. } => Expr::Int(*int),
+        ParDeclItem::Float { float, .. } => Expr::Float(*float),
+        ParDeclItem::SetOfInt { set_literal, .. } => Expr::Set(set_literal_expr(set_literal)),
+        ParDeclItem::ArrayOfBool { v, .. } => {
+            Expr::ArrayOfBool(v.iter().map(|b| BoolExpr::Bool(*b)).collect())
+        }
+        ParDeclItem::ArrayOfInt { v, .. } => {
+            Expr::ArrayOfInt(v.iter().map(|i| IntExpr::Int(*i)).collect())
+        }
+        ParDeclItem::ArrayOfFloat { v, .. } => {
+            Expr::ArrayOfFloat(v.iter().map(|f| FloatExpr::Float(*f)).collect())
+        }
+        ParDeclItem::ArrayOfSet { v, .. } => Expr::ArrayOfSet(
+            v.iter()
+                .map(|sl| SetExpr::Set(set_literal_expr(sl)))
+                .collect(),
+        ),
+    }
+}
+fn set_literal_expr(sl: &SetLiteral) -> SetLiteralExpr {
+    match sl {
+        SetLiteral::IntRange(lb, ub) => {
+            SetLiteralExpr::IntInRange(IntExpr::Int(*lb), IntExpr::Int(*ub))
+        }
+        SetLiteral::BoundedFloat(lb, ub) => {
+            SetLiteralExpr::BoundedFloat(FloatExpr::Float(*lb), FloatExpr::Float(*ub))
+        }
+        SetLiteral::SetInts(v) => {
+            SetLiteralExpr::SetInts(v.iter().map(|i| IntExpr::Int(*i)).collect())
+        }
+        SetLiteral::SetFloats(v) => {
+            SetLiteralExpr::SetFloats(v.iter().map(|f| FloatExpr::Float(*f)).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_fz_stmt, Config};
+
+    /// Declares the parameters and variables of the given statements
+    fn symbols(stmts: &[&str], config: &Config) -> SymbolTable {
+        let mut symbols = SymbolTable::default();
+        for stmt in stmts {
+            match parse_fz_stmt(stmt).unwrap().0 {
+                FzStmt::Parameter(p) => symbols.declare_parameter(&p, config),
+                FzStmt::Variable(d) => symbols.declare_variable(&d, config),
+                _ => panic!("not a declaration: {}", stmt),
+            }
+        }
+        symbols
+    }
+    fn constraint(stmt: &str) -> ConstraintItem {
+        match parse_fz_stmt(stmt).unwrap().0 {
+            FzStmt::Constraint(c) => c,
+            _ => panic!("not a constraint: {}", stmt),
+        }
+    }
+    fn variable(stmt: &str) -> VarDeclItem {
+        match parse_fz_stmt(stmt).unwrap().0 {
+            FzStmt::Variable(d) => d,
+            _ => panic!("not a variable: {}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_inline_constraint() {
+        let config = Config {
+            inline_parameters: true,
+            ..Default::default()
+        };
+        let symbols = symbols(
+            &[
+                "int: n = 4;",
+                "bool: t = true;",
+                "set of int: s = 1..3;",
+                "array [1..2] of int: cs = [2,3];",
+                "var int: x;",
+            ],
+            &config,
+        );
+        let mut c = constraint("constraint int_lin_le(cs,[x,n],n);");
+        inline_constraint(&mut c, &symbols);
+        assert_eq!(
+            c.exprs,
+            vec![
+                Expr::ArrayOfInt(vec![IntExpr::Int(2), IntExpr::Int(3)]),
+                Expr::ArrayOfInt(vec![
+                    IntExpr::VarParIdentifier("x".to_string()),
+                    IntExpr::Int(4)
+                ]),
+                Expr::Int(4),
+            ]
+        );
+        let mut c = constraint("constraint foo(t,s,x,{1,n});");
+        inline_constraint(&mut c, &symbols);
+        assert_eq!(
+            c.exprs,
+            vec![
+                Expr::Bool(true),
+                Expr::Set(SetLiteralExpr::IntInRange(IntExpr::Int(1), IntExpr::Int(3))),
+                Expr::VarParIdentifier("x".to_string()),
+                Expr::Set(SetLiteralExpr::SetInts(vec![
+                    IntExpr::Int(1),
+                    IntExpr::Int(4)
+                ])),
+            ]
+        );
+    }
+    #[test]
+    fn test_inline_needs_config() {
+        let symbols = symbols(&["int: n = 4;"], &Config::default());
+        let mut c = constraint("constraint int_le(n,n);");
+        inline_constraint(&mut c, &symbols);
+        assert_eq!(
+            c.exprs,
+            vec![
+                Expr::VarParIdentifier("n".to_string()),
+                Expr::VarParIdentifier("n".to_string())
+            ]
+        );
+    }
+    #[test]
+    fn test_inline_var_decl_item() {
+        let config = Config {
+            inline_parameters: true,
+            ..Default::default()
+        };
+        let symbols = symbols(
+            &[
+                "int: n = 4;",
+                "array [1..2] of float: fs = [1.5,2.5];",
+                "var int: x;",
+            ],
+            &config,
+        );
+        let mut d = variable("var int: y = n;");
+        inline_var_decl_item(&mut d, &symbols);
+        assert_eq!(d, variable("var int: y = 4;"));
+        let mut d = variable("array [1..2] of var int: ys = [x,n];");
+        inline_var_decl_item(&mut d, &symbols);
+        assert_eq!(d, variable("array [1..2] of var int: ys = [x,4];"));
+        let mut d = variable("array [1..2] of var float: gs = fs;");
+        inline_var_decl_item(&mut d, &symbols);
+        assert_eq!(d, variable("array [1..2] of var float: gs = [1.5,2.5];"));
+    }
+    #[test]
+    fn test_expand_constraint() {
+        let config = Config {
+            expand_arrays: true,
+            ..Default::default()
+        };
+        let symbols = symbols(
+            &[
+                "array [1..2] of int: cs = [2,3];",
+                "var int: x;",
+                "var int: y;",
+                "array [1..2] of var int: xs = [x,y];",
+                "array [1..2] of var int: ys;",
+            ],
+            &config,
+        );
+        let mut c = constraint("constraint int_lin_le(cs,xs,10);");
+        expand_constraint(&mut c, &symbols);
+        assert_eq!(
+            c.exprs[1],
+            Expr::ArrayOfInt(vec![
+                IntExpr::VarParIdentifier("x".to_string()),
+                IntExpr::VarParIdentifier("y".to_string())
+            ])
+        );
+        assert_eq!(c.exprs[0], Expr::VarParIdentifier("cs".to_string()));
+        // arrays without assignment have no elements to expand to
+        let mut c = constraint("constraint all_different_int(ys);");
+        expand_constraint(&mut c, &symbols);
+        assert_eq!(c, constraint("constraint all_different_int(ys);"));
+    }
+    #[test]
+    fn test_expand_needs_config() {
+        let symbols = symbols(
+            &["var int: x;", "array [1..1] of var int: xs = [x];"],
+            &Config::default(),
+        );
+        let mut c = constraint("constraint all_different_int(xs);");
+        expand_constraint(&mut c, &symbols);
+        assert_eq!(c, constraint("constraint all_different_int(xs);"));
+    }
+}
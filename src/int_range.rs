@@ -0,0 +1,450 @@
+use crate::references::{par_decl_id, var_decl_id_annos};
+use crate::{decimal_fraction, Config, FlatZincError, FloatMode, OverflowPolicy};
+use anyhow::Result;
+use flatzinc::*;
+use log::warn;
+
+/// Checks all integer literals of a statement against the integer range of the target system
+///
+/// This includes the lengths of array index sets, the bounds in `output_array` annotations,
+/// and the integers that floats are written as in the `scaled` and `rational` float modes.
+///
+/// Depending on the overflow policy an out of range literal is reported as error or warning.
+/// With the `string` and `split` policies the literals are encoded by `int_literal` instead.
+pub(crate) fn check_stmt(stmt: &FzStmt, par_annos: &[Annotation], config: &Config) -> Result<()> {
+    match config.overflow {
+        OverflowPolicy::Error | OverflowPolicy::Warn => {}
+        OverflowPolicy::String | OverflowPolicy::Split => return Ok(()),
+    }
+    match stmt {
+        FzStmt::Comment(_) => {}
+        FzStmt::Predicate(p) => {
+            for (pos, (par_type, id)) in p.parameters.iter().enumerate() {
+                let checker = Checker::new(
+                    config,
+                    format!("predicate {} parameter {} ({})", p.id, pos, id),
+                );
+                match par_type {
+                    PredParType::Basic(t) => checker.basic_pred_par_type(t)?,
+                    PredParType::Array { ix, par_type: t } => {
+                        if let PredIndexSet::IndexSet(len) = ix {
+                            checker.int(*len)?;
+                        }
+                        checker.basic_pred_par_type(t)?
+                    }
+                }
+            }
+        }
+        FzStmt::Parameter(p) => {
+            par_decl_item(p, config)?;
+            parameter(config, par_decl_id(p)).output_array(par_annos)?;
+        }
+        FzStmt::Variable(v) => {
+            var_decl_item(v, config)?;
+            let (id, annos) = var_decl_id_annos(v);
+            variable(config, id).output_array(annos)?;
+        }
+        FzStmt::Constraint(c) => {
+            for (pos, e) in c.exprs.iter().enumerate() {
+                Checker::new(config, format!("constraint {} argument {}", c.id, pos)).expr(e)?;
+            }
+        }
+        FzStmt::SolveItem(i) => {
+            let checker = Checker::new(config, "solve item".to_string());
+            match &i.goal {
+                Goal::Satisfy | Goal::OptimizeBool(_, _) => {}
+                Goal::OptimizeInt(_, e) => checker.int_expr(e)?,
+                Goal::OptimizeFloat(_, e) => checker.float_expr(e)?,
+                Goal::OptimizeSet(_, e) => checker.set_expr(e)?,
+            }
+        }
+    }
+    Ok(())
+}
+fn par_decl_item(item: &ParDeclItem, config: &Config) -> Result<()> {
+    match item {
+        ParDeclItem::Bool { .. } => {}
+        ParDeclItem::ArrayOfBool { id, ix, .. } => parameter(config, id).index_set(ix)?,
+        ParDeclItem::Int { id, int } => parameter(config, id).int(*int)?,
+        ParDeclItem::Float { id, float } => parameter(config, id).float(*float)?,
+        ParDeclItem::SetOfInt { id, set_literal } => {
+            parameter(config, id).set_literal(set_literal)?
+        }
+        ParDeclItem::ArrayOfInt { id, ix, v } => {
+            let checker = parameter(config, id);
+            checker.index_set(ix)?;
+            for i in v {
+                checker.int(*i)?;
+            }
+        }
+        ParDeclItem::ArrayOfFloat { id, ix, v } => {
+            let checker = parameter(config, id);
+            checker.index_set(ix)?;
+            for f in v {
+                checker.float(*f)?;
+            }
+        }
+        ParDeclItem::ArrayOfSet { id, ix, v } => {
+            let checker = parameter(config, id);
+            checker.index_set(ix)?;
+            for sl in v {
+                checker.set_literal(sl)?;
+            }
+        }
+    }
+    Ok(())
+}
+fn var_decl_item(item: &VarDeclItem, config: &Config) -> Result<()> {
+    match item {
+        VarDeclItem::Bool { .. } => {}
+        VarDeclItem::ArrayOfBool { id, ix, .. } => variable(config, id).index_set(ix)?,
+        VarDeclItem::Int { id, expr, .. } => variable(config, id).opt_int_expr(expr)?,
+        VarDeclItem::IntInRange {
+            id, lb, ub, expr, ..
+        } => {
+            let checker = variable(config, id);
+            checker.ints(&[*lb, *ub])?;
+            checker.opt_int_expr(expr)?;
+        }
+        VarDeclItem::IntInSet { id, set, expr, .. } => {
+            let checker = variable(config, id);
+            checker.ints(set)?;
+            checker.opt_int_expr(expr)?;
+        }
+        VarDeclItem::Float { id, expr, .. } => variable(config, id).opt_float_expr(expr)?,
+        VarDeclItem::BoundedFloat {
+            id, lb, ub, expr, ..
+        } => {
+            let checker = variable(config, id);
+            checker.float(*lb)?;
+            checker.float(*ub)?;
+            checker.opt_float_expr(expr)?;
+        }
+        VarDeclItem::SetOfInt { id, expr, .. } => variable(config, id).opt_set_expr(expr)?,
+        VarDeclItem::SubSetOfIntRange {
+            id, lb, ub, expr, ..
+        } => {
+            let checker = variable(config, id);
+            checker.ints(&[*lb, *ub])?;
+            checker.opt_set_expr(expr)?;
+        }
+        VarDeclItem::SubSetOfIntSet { id, set, expr, .. } => {
+            let checker = variable(config, id);
+            checker.ints(set)?;
+            checker.opt_set_expr(expr)?;
+        }
+        VarDeclItem::ArrayOfInt {
+            id, ix, array_expr, ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.array_of_int_expr(array_expr)?;
+        }
+        VarDeclItem::ArrayOfIntInRange {
+            id,
+            ix,
+            lb,
+            ub,
+            array_expr,
+            ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.ints(&[*lb, *ub])?;
+            checker.array_of_int_expr(array_expr)?;
+        }
+        VarDeclItem::ArrayOfIntInSet {
+            id,
+            ix,
+            set,
+            array_expr,
+            ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.ints(set)?;
+            checker.array_of_int_expr(array_expr)?;
+        }
+        VarDeclItem::ArrayOfFloat {
+            id, ix, array_expr, ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.array_of_float_expr(array_expr)?;
+        }
+        VarDeclItem::ArrayOfBoundedFloat {
+            id,
+            ix,
+            lb,
+            ub,
+            array_expr,
+            ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.float(*lb)?;
+            checker.float(*ub)?;
+            checker.array_of_float_expr(array_expr)?;
+        }
+        VarDeclItem::ArrayOfSet {
+            id, ix, array_expr, ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.array_of_set_expr(array_expr)?;
+        }
+        VarDeclItem::ArrayOfSubSetOfIntRange {
+            id,
+            ix,
+            lb,
+            ub,
+            array_expr,
+            ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.ints(&[*lb, *ub])?;
+            checker.array_of_set_expr(array_expr)?;
+        }
+        VarDeclItem::ArrayOfSubSetOfIntSet {
+            id,
+            ix,
+            set,
+            array_expr,
+            ..
+        } => {
+            let checker = variable(config, id);
+            checker.index_set(ix)?;
+            checker.ints(set)?;
+            checker.array_of_set_expr(array_expr)?;
+        }
+    }
+    Ok(())
+}
+fn parameter<'a>(config: &'a Config, id: &str) -> Checker<'a> {
+    Checker::new(config, format!("parameter {}", id))
+}
+fn variable<'a>(config: &'a Config, id: &str) -> Checker<'a> {
+    Checker::new(config, format!("variable {}", id))
+}
+struct Checker<'a> {
+    config: &'a Config,
+    location: String,
+}
+impl<'a> Checker<'a> {
+    fn new(config: &'a Config, location: String) -> Self {
+        Checker { config, location }
+    }
+    fn int(&self, value: i128) -> Result<()> {
+        if self.config.int_range.0 <= value && value <= self.config.int_range.1 {
+            return Ok(());
+        }
+        let err = FlatZincError::IntegerOutOfRange {
+            value,
+            location: self.location.clone(),
+            min: self.config.int_range.0,
+            max: self.config.int_range.1,
+        };
+        match self.config.overflow {
+            OverflowPolicy::Error => Err(err.into()),
+            _ => {
+                warn!("{}", err);
+                Ok(())
+            }
+        }
+    }
+    fn float(&self, value: f64) -> Result<()> {
+        match self.config.floats {
+            FloatMode::String => {}
+            FloatMode::Scaled(factor) => self.int((value * factor as f64).round() as i128)?,
+            FloatMode::Rational => {
+                if let Some((num, den)) = decimal_fraction(value) {
+                    self.int(num)?;
+                    self.int(den)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn index_set(&self, IndexSet(len): &IndexSet) -> Result<()> {
+        self.int(*len)
+    }
+    /// Checks the bounds of the index sets of an `output_array` annotation
+    fn output_array(&self, annos: &[Annotation]) -> Result<()> {
+        for a in annos.iter().filter(|a| a.id == "output_array") {
+            if let Some(AnnExpr::Expr(Expr::ArrayOfSet(v))) = a.expressions.first() {
+                for e in v {
+                    self.set_expr(e)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn ints(&self, values: &[i128]) -> Result<()> {
+        for i in values {
+            self.int(*i)?;
+        }
+        Ok(())
+    }
+    fn int_expr(&self, e: &IntExpr) -> Result<()> {
+        match e {
+            IntExpr::Int(i) => self.int(*i),
+            IntExpr::VarParIdentifier(_) => Ok(()),
+        }
+    }
+    fn opt_int_expr(&self, e: &Option<IntExpr>) -> Result<()> {
+        match e {
+            Some(e) => self.int_expr(e),
+            None => Ok(()),
+        }
+    }
+    fn float_expr(&self, e: &FloatExpr) -> Result<()> {
+        match e {
+            FloatExpr::Float(f) => self.float(*f),
+            FloatExpr::VarParIdentifier(_) => Ok(()),
+        }
+    }
+    fn opt_float_expr(&self, e: &Option<FloatExpr>) -> Result<()> {
+        match e {
+            Some(e) => self.float_expr(e),
+            None => Ok(()),
+        }
+    }
+    fn set_expr(&self, e: &SetExpr) -> Result<()> {
+        match e {
+            SetExpr::Set(sl) => self.set_literal_expr(sl),
+            SetExpr::VarParIdentifier(_) => Ok(()),
+        }
+    }
+    fn opt_set_expr(&self, e: &Option<SetExpr>) -> Result<()> {
+        match e {
+            Some(e) => self.set_expr(e),
+            None => Ok(()),
+        }
+    }
+    fn set_literal(&self, sl: &SetLiteral) -> Result<()> {
+        match sl {
+            SetLiteral::IntRange(lb, ub) => self.ints(&[*lb, *ub]),
+            SetLiteral::SetInts(v) => self.ints(v),
+            SetLiteral::BoundedFloat(lb, ub) => {
+                self.float(*lb)?;
+                self.float(*ub)
+            }
+            SetLiteral::SetFloats(v) => {
+                for f in v {
+                    self.float(*f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+    fn set_literal_expr(&self, sl: &SetLiteralExpr) -> Result<()> {
+        match sl {
+            SetLiteralExpr::IntInRange(lb, ub) => {
+                self.int_expr(lb)?;
+                self.int_expr(ub)
+            }
+            SetLiteralExpr::SetInts(v) => {
+                for e in v {
+                    self.int_expr(e)?;
+                }
+                Ok(())
+            }
+            SetLiteralExpr::BoundedFloat(lb, ub) => {
+                self.float_expr(lb)?;
+                self.float_expr(ub)
+            }
+            SetLiteralExpr::SetFloats(v) => {
+                for e in v {
+                    self.float_expr(e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+    fn array_of_int_expr(&self, e: &Option<ArrayOfIntExpr>) -> Result<()> {
+        if let Some(ArrayOfIntExpr::Array(v)) = e {
+            for e in v {
+                self.int_expr(e)?;
+            }
+        }
+        Ok(())
+    }
+    fn array_of_float_expr(&self, e: &Option<ArrayOfFloatExpr>) -> Result<()> {
+        if let Some(ArrayOfFloatExpr::Array(v)) = e {
+            for e in v {
+                self.float_expr(e)?;
+            }
+        }
+        Ok(())
+    }
+    fn array_of_set_expr(&self, e: &Option<ArrayOfSetExpr>) -> Result<()> {
+        if let Some(ArrayOfSetExpr::Array(v)) = e {
+            for e in v {
+                self.set_expr(e)?;
+            }
+        }
+        Ok(())
+    }
+    fn expr(&self, e: &Expr) -> Result<()> {
+        match e {
+            Expr::VarParIdentifier(_) | Expr::Bool(_) | Expr::ArrayOfBool(_) => Ok(()),
+            Expr::Int(i) => self.int(*i),
+            Expr::Float(f) => self.float(*f),
+            Expr::Set(sl) => self.set_literal_expr(sl),
+            Expr::ArrayOfInt(v) => {
+                for e in v {
+                    self.int_expr(e)?;
+                }
+                Ok(())
+            }
+            Expr::ArrayOfFloat(v) => {
+                for e in v {
+                    self.float_expr(e)?;
+                }
+                Ok(())
+            }
+            Expr::ArrayOfSet(v) => {
+                for e in v {
+                    self.set_expr(e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+    fn basic_var_type(&self, t: &BasicVarType) -> Result<()> {
+        match t {
+            BasicVarType::BasicType(_) => Ok(()),
+            BasicVarType::IntInRange(lb, ub) | BasicVarType::SubSetOfIntRange(lb, ub) => {
+                self.ints(&[*lb, *ub])
+            }
+            BasicVarType::IntInSet(set) | BasicVarType::SubSetOfIntSet(set) => self.ints(set),
+            BasicVarType::BoundedFloat(lb, ub) => {
+                self.float(*lb)?;
+                self.float(*ub)
+            }
+        }
+    }
+    fn basic_pred_par_type(&self, t: &BasicPredParType) -> Result<()> {
+        match t {
+            BasicPredParType::BasicParType(_) | BasicPredParType::VarSetOfInt => Ok(()),
+            BasicPredParType::BasicVarType(t) => self.basic_var_type(t),
+            BasicPredParType::IntInRange(lb, ub) | BasicPredParType::SubSetOfIntRange(lb, ub) => {
+                self.ints(&[*lb, *ub])
+            }
+            BasicPredParType::IntInSet(set) | BasicPredParType::SubSetOfIntSet(set) => {
+                self.ints(set)
+            }
+            BasicPredParType::BoundedFloat(lb, ub) => {
+                self.float(*lb)?;
+                self.float(*ub)
+            }
+            BasicPredParType::FloatInSet(set) => {
+                for f in set {
+                    self.float(*f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::fmt::Display;
+
+/// Returns `s` as JSON string
+pub(crate) fn string(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+/// Returns a JSON object with the given keys and already encoded values
+pub(crate) fn object<K: Display, V: Display>(entries: impl IntoIterator<Item = (K, V)>) -> String {
+    let entries: Vec<String> = entries
+        .into_iter()
+        .map(|(k, v)| format!("{}:{}", string(&k.to_string()), v))
+        .collect();
+    format!("{{{}}}", entries.join(","))
+}
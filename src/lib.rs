@@ -1,9 +1,40 @@
 use anyhow::Result;
 use flatzinc::*;
 use log::warn;
+use std::borrow::Cow;
 use std::io::Write;
 use thiserror::Error;
 
+mod alias;
+mod components;
+mod domain;
+mod duplicates;
+mod elimination;
+mod features;
+mod graph;
+mod incidence;
+mod inline;
+mod int_range;
+mod json;
+mod presolve;
+mod references;
+mod signatures;
+mod slice;
+mod stats;
+mod support;
+mod symbols;
+
+pub use components::{ComponentAssignment, Components};
+pub use duplicates::Duplicates;
+pub use elimination::{Elimination, RemovedDeclaration};
+pub use features::Features;
+pub use graph::IncidenceGraph;
+pub use presolve::Presolve;
+pub use slice::Slice;
+pub use stats::Stats;
+pub use support::{SupportReport, SupportedConstraints, UnsupportedConstraint};
+pub use symbols::{BaseType, Symbol, SymbolKind, SymbolTable};
+
 #[derive(Error, Debug)]
 pub enum FlatZincError {
     #[error("More than one solve item")]
@@ -12,12 +43,377 @@ pub enum FlatZincError {
     NoSolveItem,
     #[error("ParseError: {msg}")]
     ParseError { msg: String },
+    #[error("Integer {value} in {location} is outside of the range {min}..{max}")]
+    IntegerOutOfRange {
+        value: i128,
+        location: String,
+        min: i128,
+        max: i128,
+    },
+    #[error("Expected an array of index sets in the output_array annotation of {id}")]
+    ExpectedIndexSets { id: String },
+    #[error("Unsupported index set {pos} in the output_array annotation of {id}: {set}")]
+    UnsupportedIndexSet { id: String, pos: usize, set: String },
+    #[error("Undefined identifier {id} in {location}")]
+    UndefinedIdentifier { id: String, location: String },
+    #[error("Unknown variable {id}")]
+    UnknownVariable { id: String },
+    #[error("Duplicate declaration of {id}")]
+    DuplicateDeclaration { id: String },
+    #[error("Unknown constraint {name} in constraint {constraint}")]
+    UnknownConstraint { constraint: String, name: String },
+    #[error("Constraint {constraint} ({name}) expects {expected} arguments, found {found}")]
+    ArityMismatch {
+        constraint: String,
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error(
+        "Argument {pos} of constraint {constraint} ({name}) has type {found}, expected {expected}"
+    )]
+    ArgumentTypeMismatch {
+        constraint: String,
+        name: String,
+        pos: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("{count} constraint names are not supported by the encoding")]
+    UnsupportedConstraints { count: usize },
+    #[error("Empty domain {domain} of {location}")]
+    EmptyDomain { location: String, domain: String },
+    #[error("Value {value} of {location} is outside of its domain {domain}")]
+    ValueOutsideDomain {
+        value: String,
+        location: String,
+        domain: String,
+    },
+}
+
+/// State of a conversion that is carried from one statement to the next
+#[derive(Debug, Default)]
+pub struct State {
+    pub constraint_counter: usize,
+    pub level: i32,
+    pub symbols: SymbolTable,
+    pub duplicates: Duplicates,
+}
+
+/// Options controlling how facts are written
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub identifiers: IdentifierMode,
+    pub floats: FloatMode,
+    /// Range of integers supported by the target system, clingo uses 32-bit integers
+    pub int_range: (i128, i128),
+    pub overflow: OverflowPolicy,
+    /// Template of the atom holding the value of a variable, e.g. `val(X,V)`.
+    /// If set, `#show` directives are emitted for all output variables,
+    /// with `X` replaced by the identifier of the variable.
+    pub show: Option<String>,
+    /// Emit the type of every constraint argument as `constraint_type_at(C,Pos,Type)`
+    pub typed_constraints: bool,
+    /// Replace references to parameters in constraints and variable assignments by their values
+    pub inline_parameters: bool,
+    /// Replace references to variable arrays in constraints by their elements
+    pub expand_arrays: bool,
+    /// Replace variables assigned to other variables by the representative of their
+    /// equivalence class and emit `alias(Variable,Representative)` facts
+    pub resolve_aliases: bool,
+    /// Emit `variable_domain(Variable,Value)` for integer variables with at most this many values
+    pub domain_threshold: Option<u64>,
+    /// Emit `constraint_set_element(C,Pos,Value)` for integer set arguments with at most this many values
+    pub set_threshold: Option<u64>,
+    /// Report inconsistencies of the model as warnings instead of errors
+    pub lenient: bool,
+    /// Report references to undeclared identifiers and duplicate declarations.
+    /// This requires that all statements of a model are converted with the same state.
+    /// Disabled by default, so that single statements can be converted on their own.
+    pub check_identifiers: bool,
+    /// Check the arity and argument types of constraints against the declared predicates
+    /// and the FlatZinc builtins
+    pub check_signatures: bool,
+    /// Emit `constraint_variable(C,Variable)` for every variable referenced by a constraint,
+    /// including the elements of referenced variable arrays
+    pub incidence: bool,
+    /// Treatment of constraints that are identical to an earlier constraint
+    pub duplicates: DuplicatePolicy,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            identifiers: IdentifierMode::default(),
+            floats: FloatMode::default(),
+            int_range: (i32::MIN as i128, i32::MAX as i128),
+            overflow: OverflowPolicy::default(),
+            show: None,
+            typed_constraints: false,
+            inline_parameters: false,
+            expand_arrays: false,
+            resolve_aliases: false,
+            domain_threshold: None,
+            set_threshold: None,
+            lenient: false,
+            check_identifiers: false,
+            check_signatures: false,
+            incidence: false,
+            duplicates: DuplicatePolicy::default(),
+        }
+    }
+}
+
+/// Representation of FlatZinc identifiers in the emitted facts
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IdentifierMode {
+    /// Always emit identifiers as escaped strings, e.g. `"X_1"`
+    #[default]
+    String,
+    /// Emit identifiers that are valid clingo constants as is, e.g. `x_1`,
+    /// and fall back to strings for all others
+    Constant,
+}
+impl std::str::FromStr for IdentifierMode {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(IdentifierMode::String),
+            "constant" => Ok(IdentifierMode::Constant),
+            _ => Err(format!("unknown identifier mode: {}", s)),
+        }
+    }
+}
+
+/// Representation of float values in the emitted facts
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FloatMode {
+    /// Emit floats as strings, e.g. `"1.5"`
+    #[default]
+    String,
+    /// Emit floats as integers multiplied by the given factor, e.g. `1500` for a factor of 1000
+    Scaled(u64),
+    /// Emit floats as exact fractions of their decimal literal, e.g. `rational(3,2)`
+    Rational,
+}
+
+/// Treatment of integers outside of `Config::int_range`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverflowPolicy {
+    /// Abort with an error
+    Error,
+    /// Print a warning and emit the integer as is
+    #[default]
+    Warn,
+    /// Emit the integer as string, e.g. `"3000000000"`
+    String,
+    /// Split the integer into a sign and limbs in base 10^9,
+    /// e.g. `big(-1,3,0)` for -3000000000
+    Split,
+}
+impl std::str::FromStr for OverflowPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(OverflowPolicy::Error),
+            "warn" => Ok(OverflowPolicy::Warn),
+            "string" => Ok(OverflowPolicy::String),
+            "split" => Ok(OverflowPolicy::Split),
+            _ => Err(format!("unknown overflow policy: {}", s)),
+        }
+    }
+}
+
+/// Treatment of constraints identical to an earlier constraint
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DuplicatePolicy {
+    /// Emit duplicates like all other constraints
+    #[default]
+    Keep,
+    /// Emit duplicates together with `duplicate_of(C,First)`
+    Report,
+    /// Drop duplicates
+    Remove,
+}
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(DuplicatePolicy::Keep),
+            "report" => Ok(DuplicatePolicy::Report),
+            "remove" => Ok(DuplicatePolicy::Remove),
+            _ => Err(format!("unknown duplicate policy: {}", s)),
+        }
+    }
+}
+
+/// Categories of the emitted facts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Predicates,
+    Parameters,
+    Variables,
+    Constraints,
+    /// Facts derived from the `output_var` and `output_array` annotations
+    Outputs,
+    Solve,
+}
+impl Category {
+    pub const ALL: [Category; 6] = [
+        Category::Predicates,
+        Category::Parameters,
+        Category::Variables,
+        Category::Constraints,
+        Category::Outputs,
+        Category::Solve,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Predicates => "predicates",
+            Category::Parameters => "parameters",
+            Category::Variables => "variables",
+            Category::Constraints => "constraints",
+            Category::Outputs => "outputs",
+            Category::Solve => "solve",
+        }
+    }
+    /// Writes an `#include` directive for the file `<category>.lp` of every category
+    pub fn write_index(mut out: impl Write) -> std::io::Result<()> {
+        for category in Category::ALL.iter() {
+            writeln!(out, "#include \"{}.lp\".", category.name())?;
+        }
+        Ok(())
+    }
+    /// Category of the statements at the given level, used for comments
+    fn from_level(level: i32) -> Self {
+        match level {
+            i32::MIN..=1 => Category::Predicates,
+            2 => Category::Parameters,
+            3 => Category::Variables,
+            4 => Category::Constraints,
+            _ => Category::Solve,
+        }
+    }
+}
+
+/// Destination of the emitted facts
+///
+/// Every `Write` is a sink that receives the facts of all categories.
+pub trait Sink {
+    /// Returns the writer for the facts of `category`,
+    /// it is requested once for every statement
+    fn writer(&mut self, category: Category) -> std::io::Result<&mut dyn Write>;
+    /// Returns the writer for comments between the statements of `category`
+    fn comment_writer(&mut self, category: Category) -> std::io::Result<&mut dyn Write> {
+        self.writer(category)
+    }
+    /// Returns true if the facts of each category are written to a separate writer
+    fn separates_categories(&self) -> bool {
+        false
+    }
+}
+impl<W: Write> Sink for W {
+    fn writer(&mut self, _category: Category) -> std::io::Result<&mut dyn Write> {
+        Ok(self)
+    }
+}
+
+/// Sink that writes the facts of each category to a separate writer
+pub struct CategorySinks<W: Write> {
+    writers: Vec<W>,
+}
+impl<W: Write> CategorySinks<W> {
+    /// Creates the sinks, `new_writer` is called once for every category in `Category::ALL`
+    pub fn new(
+        mut new_writer: impl FnMut(Category) -> std::io::Result<W>,
+    ) -> std::io::Result<Self> {
+        let mut writers = vec![];
+        for category in Category::ALL.iter() {
+            writers.push(new_writer(*category)?);
+        }
+        Ok(CategorySinks { writers })
+    }
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+    /// Returns the writers in the order of `Category::ALL`
+    pub fn into_writers(self) -> Vec<W> {
+        self.writers
+    }
+}
+impl<W: Write> Sink for CategorySinks<W> {
+    fn writer(&mut self, category: Category) -> std::io::Result<&mut dyn Write> {
+        Ok(&mut self.writers[category as usize])
+    }
+    fn separates_categories(&self) -> bool {
+        true
+    }
+}
+
+/// Sink that wraps the facts of each category in a clingo `#program` block
+///
+/// The blocks are named `fzn_<category>`, e.g. `#program fzn_variables.`.
+/// If a batch size is given the constraints are split into numbered blocks
+/// `fzn_constraints_1`, `fzn_constraints_2`, ... of at most that many constraints.
+pub struct ProgramSink<S: Sink> {
+    inner: S,
+    batch_size: Option<usize>,
+    /// The current block of every writer of the inner sink
+    current: Vec<Option<(Category, usize)>>,
+    constraints: usize,
+}
+impl<S: Sink> ProgramSink<S> {
+    pub fn new(inner: S, batch_size: Option<usize>) -> Self {
+        ProgramSink {
+            inner,
+            batch_size,
+            current: vec![None; Category::ALL.len()],
+            constraints: 0,
+        }
+    }
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+impl<S: Sink> Sink for ProgramSink<S> {
+    fn writer(&mut self, category: Category) -> std::io::Result<&mut dyn Write> {
+        let mut part = 0;
+        if category == Category::Constraints {
+            if let Some(size) = self.batch_size {
+                part = self.constraints / size.max(1) + 1;
+            }
+            self.constraints += 1;
+        }
+        let writer = if self.inner.separates_categories() {
+            category as usize
+        } else {
+            0
+        };
+        let buf = self.inner.writer(category)?;
+        if self.current[writer] != Some((category, part)) {
+            self.current[writer] = Some((category, part));
+            if part > 0 {
+                writeln!(buf, "#program fzn_{}_{}.", category.name(), part)?;
+            } else {
+                writeln!(buf, "#program fzn_{}.", category.name())?;
+            }
+        }
+        Ok(buf)
+    }
+    fn comment_writer(&mut self, category: Category) -> std::io::Result<&mut dyn Write> {
+        self.inner.comment_writer(category)
+    }
+    fn separates_categories(&self) -> bool {
+        self.inner.separates_categories()
+    }
 }
 
 #[test]
 fn test_predicates() {
-    let mut counter = 0;
-    let mut level = 0;
+    let mut state = State::default();
+    let config = Config::default();
     let mut res = Vec::new();
     write_fz_stmt(
         &mut res,
@@ -25,8 +421,8 @@ fn test_predicates() {
                    set of int: d, set of {1,2,3}: e, set of 1..11: f,\
                    array [int] of int:g, array [int] of {1,2,3}:h, array [int] of 1..11:i,\
                    array [int] of set of int:j);",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -55,14 +451,14 @@ fn test_predicates() {
 }
 #[test]
 fn test_variables() {
-    let mut counter = 0;
-    let mut level = 0;
+    let mut state = State::default();
+    let config = Config::default();
     let mut res = Vec::new();
     write_fz_stmt(
         &mut res,
         "var int : a :: output_var = 1;",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -73,13 +469,13 @@ fn test_variables() {
             .to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "var 1..3 : a;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "var 1..3 : a;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "variable_type(\"a\",int,range,(value,1,value,3)).\n".to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "var {1,2,3} : a;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "var {1,2,3} : a;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "variable_type(\"a\",int,set,(value,1)).\n\
@@ -88,7 +484,7 @@ fn test_variables() {
             .to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "var float : b = 1.0;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "var float : b = 1.0;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "variable_type(\"b\",float).\n\
@@ -96,7 +492,7 @@ fn test_variables() {
             .to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "var 0.5..1.5: b = 1.0;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "var 0.5..1.5: b = 1.0;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "variable_type(\"b\",float,(bounds,value,\"0.5\",value,\"1.5\")).\n\
@@ -104,7 +500,7 @@ fn test_variables() {
             .to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "var bool : c = true;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "var bool : c = true;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "variable_type(\"c\",bool).\n\
@@ -115,8 +511,8 @@ fn test_variables() {
     write_fz_stmt(
         &mut res,
         "array [1..2] of var int : d = [42,23];",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -130,8 +526,8 @@ fn test_variables() {
     write_fz_stmt(
         &mut res,
         "array [1..2] of var float : e :: output_array([1..2, 1..2]) = [42.1,23.1];",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -147,8 +543,8 @@ fn test_variables() {
     write_fz_stmt(
         &mut res,
         "var set of 17..42: f = {17,23};",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -162,8 +558,8 @@ fn test_variables() {
     write_fz_stmt(
         &mut res,
         "var set of {17,23,100}: f = {17,23};",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -191,8 +587,8 @@ fn test_variables() {
     write_fz_stmt(
         &mut res,
         "array [1..3] of var set of 17..42: h = [{42,17},23..X,{}];", //TODO: check empty set
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -207,22 +603,22 @@ fn test_variables() {
 }
 #[test]
 fn test_parameters() {
-    let mut counter = 0;
-    let mut level = 0;
+    let mut state = State::default();
+    let config = Config::default();
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "int : a = 1;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "int : a = 1;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "parameter_value(\"a\",value,1).\n".to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "float : b = 1.1;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "float : b = 1.1;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "parameter_value(\"b\",value,\"1.1\").\n".to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(&mut res, "bool : c = true;", &mut counter, &mut level).unwrap();
+    write_fz_stmt(&mut res, "bool : c = true;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "parameter_value(\"c\",value,true).\n".to_string()
@@ -231,8 +627,8 @@ fn test_parameters() {
     write_fz_stmt(
         &mut res,
         "array [1..2] of int : d = [42,23];",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -245,8 +641,8 @@ fn test_parameters() {
     write_fz_stmt(
         &mut res,
         "array [1..2] of float : e = [42.1,23.0];",
-        &mut counter,
-        &mut level,
+        &mut state,
+        &config,
     )
     .unwrap();
     assert_eq!(
@@ -256,13 +652,7 @@ fn test_parameters() {
             .to_string()
     );
     let mut res = Vec::new();
-    write_fz_stmt(
-        &mut res,
-        "set of int: f = 23..42;",
-        &mut counter,
-        &mut level,
-    )
-    .unwrap();
+    write_fz_stmt(&mut res, "set of int: f = 23..42;", &mut state, &config).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
         "parameter_value(\"f\",range,(value,23,value,42)).\n".to_string()
@@ -280,110 +670,1264 @@ fn test_parameters() {
     //     "parameter_value(\"g\",set,(value,\"23\"))).\nparameter_value(\"g\",set,(value,\"42.1\"))).\n".to_string()
     // );
     let mut res = Vec::new();
-    write_fz_stmt(
-        &mut res,
-        "array [1..3] of set of int : h = [{42,17},1..5,{}];",
-        &mut counter,
-        &mut level,
-    )
-    .unwrap();
+    write_fz_stmt(
+        &mut res,
+        "array [1..3] of set of int : h = [{42,17},1..5,{}];",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "parameter_value(\"h\",array,(0,set,(value,42))).\n\
+         parameter_value(\"h\",array,(0,set,(value,17))).\n\
+         parameter_value(\"h\",array,(1,range,(value,1,value,5))).\n\
+         parameter_value(\"h\",array,(2,empty_set)).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_constraints() {
+    let mut state = State::default();
+    let config = Config::default();
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "constraint bla(42,42.1,true,a,[42,17,X],{X,34},37..48,[{42,17},17..34,{X,Y}]);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "constraint(c1,\"bla\").\n\
+         constraint_value(c1,0,value,42).\n\
+         constraint_value(c1,1,value,\"42.1\").\n\
+         constraint_value(c1,2,value,true).\n\
+         constraint_value(c1,3,var,\"a\").\n\
+         constraint_value(c1,4,array,(0,value,42)).\n\
+         constraint_value(c1,4,array,(1,value,17)).\n\
+         constraint_value(c1,4,array,(2,var,\"X\")).\n\
+         constraint_value(c1,5,set,(var,\"X\")).\n\
+         constraint_value(c1,5,set,(value,34)).\n\
+         constraint_value(c1,6,range,(value,37,value,48)).\n\
+         constraint_value(c1,7,array,(0,set,(value,42))).\n\
+         constraint_value(c1,7,array,(0,set,(value,17))).\n\
+         constraint_value(c1,7,array,(1,range,(value,17,value,34))).\n\
+         constraint_value(c1,7,array,(2,set,(var,\"X\"))).\n\
+         constraint_value(c1,7,array,(2,set,(var,\"Y\"))).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_identifiers() {
+    let mut state = State::default();
+    let config = Config {
+        identifiers: IdentifierMode::Constant,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "constraint int_le(x,[_y,X_1]);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "constraint(c1,int_le).\n\
+         constraint_value(c1,0,var,x).\n\
+         constraint_value(c1,1,array,(0,var,_y)).\n\
+         constraint_value(c1,1,array,(1,var,\"X_1\")).\n"
+            .to_string()
+    );
+    let mut res = Vec::new();
+    write_fz_stmt(&mut res, "%* not a block", &mut state, &config).unwrap();
+    assert_eq!(std::str::from_utf8(&res).unwrap(), "% * not a block\n");
+    assert_eq!(string("a\"b\\c\nd"), "\"a\\\"b\\\\c\\nd\"");
+}
+#[test]
+fn test_floats() {
+    let mut state = State::default();
+    let config = Config {
+        floats: FloatMode::Scaled(100),
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    write_fz_stmt(&mut res, "float : a = 1.1;", &mut state, &config).unwrap();
+    write_fz_stmt(&mut res, "var 0.5..1.5: b;", &mut state, &config).unwrap();
+    write_fz_stmt(&mut res, "float : c = 0.125;", &mut state, &config).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "parameter_value(\"a\",value,110).\n\
+         variable_type(\"b\",float,(bounds,value,50,value,150)).\n\
+         parameter_value(\"c\",value,13).\n"
+            .to_string()
+    );
+    let config = Config {
+        floats: FloatMode::Rational,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "constraint float_lin_le([1.5,-2.0],[X,Y],0.125);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "constraint(c1,\"float_lin_le\").\n\
+         constraint_value(c1,0,array,(0,value,rational(3,2))).\n\
+         constraint_value(c1,0,array,(1,value,rational(-2,1))).\n\
+         constraint_value(c1,1,array,(0,var,\"X\")).\n\
+         constraint_value(c1,1,array,(1,var,\"Y\")).\n\
+         constraint_value(c1,2,value,rational(1,8)).\n"
+            .to_string()
+    );
+    let config = Config {
+        floats: FloatMode::Rational,
+        overflow: OverflowPolicy::String,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "float: a = 0.3333333333333333;",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "parameter_value(\"a\",value,rational(\"3333333333333333\",\"10000000000000000\")).\n"
+    );
+}
+#[test]
+fn test_int_overflow() {
+    let mut state = State::default();
+    let config = Config {
+        overflow: OverflowPolicy::Error,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    assert!(write_fz_stmt(&mut res, "var 1..3000000000: x;", &mut state, &config).is_err());
+    let checked = |stmt: &str, config: &Config| {
+        write_fz_stmt(&mut Vec::new(), stmt, &mut State::default(), config)
+    };
+    assert!(checked("array [1..3000000000] of var int: xs;", &config).is_err());
+    assert!(checked(
+        "array [1..2] of var int: xs :: output_array([1..3000000000]) = [x,x];",
+        &config
+    )
+    .is_err());
+    assert!(checked(
+        "array [1..2] of int: a :: output_array([{3000000000,3000000001}]) = [1,2];",
+        &config
+    )
+    .is_err());
+    assert!(checked("float: a = 0.3333333333333333;", &config).is_ok());
+    let rational = Config {
+        floats: FloatMode::Rational,
+        ..config
+    };
+    assert!(checked("float: a = 0.3333333333333333;", &rational).is_err());
+    assert!(checked("float: a = 0.25;", &rational).is_ok());
+    let config = Config {
+        overflow: OverflowPolicy::Split,
+        ..Default::default()
+    };
+    write_fz_stmt(
+        &mut res,
+        "array [1..2] of int: a = [-3000000000,2147483647];",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "parameter_value(\"a\",array,(0,value,big(-1,3,0))).\n\
+         parameter_value(\"a\",array,(1,value,2147483647)).\n"
+            .to_string()
+    );
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "array [1..3000000000] of var int: xs :: output_array([1..3000000000]);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"xs\",array(big(1,3,0),int)).\n\
+         output_array(\"xs\",0,(1,big(1,3,0))).\n"
+    );
+}
+#[test]
+fn test_programs() {
+    let mut state = State::default();
+    let config = Config::default();
+    let mut out = ProgramSink::new(Vec::new(), Some(1));
+    for stmt in &[
+        "var bool : a :: output_var;",
+        "constraint bool_eq(a,true);",
+        "constraint bool_eq(a,false);",
+    ] {
+        write_fz_stmt(&mut out, stmt, &mut state, &config).unwrap();
+    }
+    assert_eq!(
+        std::str::from_utf8(&out.into_inner()).unwrap(),
+        "#program fzn_variables.\n\
+         variable_type(\"a\",bool).\n\
+         #program fzn_outputs.\n\
+         output_var(\"a\").\n\
+         #program fzn_constraints_1.\n\
+         constraint(c1,\"bool_eq\").\n\
+         constraint_value(c1,0,var,\"a\").\n\
+         constraint_value(c1,1,value,true).\n\
+         #program fzn_constraints_2.\n\
+         constraint(c2,\"bool_eq\").\n\
+         constraint_value(c2,0,var,\"a\").\n\
+         constraint_value(c2,1,value,false).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_category_sinks() {
+    let stmts = [
+        "predicate my_pred(var int: x);",
+        "int: k = 3;",
+        "var 1..3: x :: output_var;",
+        "constraint int_le(x,k);",
+        "constraint int_le(k,x);",
+        "solve satisfy;",
+    ];
+    let mut state = State::default();
+    let config = Config::default();
+    let mut out = CategorySinks::new(|_| Ok(Vec::new())).unwrap();
+    for stmt in &stmts {
+        write_fz_stmt(&mut out, stmt, &mut state, &config).unwrap();
+    }
+    let files: Vec<String> = out
+        .into_writers()
+        .into_iter()
+        .map(|w| String::from_utf8(w).unwrap())
+        .collect();
+    assert_eq!(
+        files,
+        [
+            "predicate(\"my_pred\").\n\
+             predicate_parameter(\"my_pred\",0,\"x\",int).\n",
+            "parameter_value(\"k\",value,3).\n",
+            "variable_type(\"x\",int,range,(value,1,value,3)).\n",
+            "constraint(c1,\"int_le\").\n\
+             constraint_value(c1,0,var,\"x\").\n\
+             constraint_value(c1,1,var,\"k\").\n\
+             constraint(c2,\"int_le\").\n\
+             constraint_value(c2,0,var,\"k\").\n\
+             constraint_value(c2,1,var,\"x\").\n",
+            "output_var(\"x\").\n",
+            "solve(satisfy).\n",
+        ]
+    );
+    let mut state = State::default();
+    let mut out = ProgramSink::new(CategorySinks::new(|_| Ok(Vec::new())).unwrap(), Some(1));
+    for stmt in &stmts[1..] {
+        write_fz_stmt(&mut out, stmt, &mut state, &config).unwrap();
+    }
+    let files: Vec<String> = out
+        .into_inner()
+        .into_writers()
+        .into_iter()
+        .map(|w| String::from_utf8(w).unwrap())
+        .collect();
+    assert_eq!(files[0], "");
+    assert_eq!(
+        files[1],
+        "#program fzn_parameters.\nparameter_value(\"k\",value,3).\n"
+    );
+    assert_eq!(
+        files[3],
+        "#program fzn_constraints_1.\n\
+         constraint(c1,\"int_le\").\n\
+         constraint_value(c1,0,var,\"x\").\n\
+         constraint_value(c1,1,var,\"k\").\n\
+         #program fzn_constraints_2.\n\
+         constraint(c2,\"int_le\").\n\
+         constraint_value(c2,0,var,\"k\").\n\
+         constraint_value(c2,1,var,\"x\").\n"
+    );
+    assert_eq!(files[5], "#program fzn_solve.\nsolve(satisfy).\n");
+    let mut index = Vec::new();
+    Category::write_index(&mut index).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&index).unwrap(),
+        "#include \"predicates.lp\".\n\
+         #include \"parameters.lp\".\n\
+         #include \"variables.lp\".\n\
+         #include \"constraints.lp\".\n\
+         #include \"outputs.lp\".\n\
+         #include \"solve.lp\".\n"
+    );
+}
+#[test]
+fn test_show() {
+    let mut state = State::default();
+    let config = Config {
+        show: Some("val(X,V)".to_string()),
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "array [1..2] of var int: xs :: output_array([1..2]) = [X_1,3];",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    write_fz_stmt(&mut res, "solve satisfy;", &mut state, &config).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"xs\",array(2,int)).\n\
+         variable_value(\"xs\",array,(0,var,\"X_1\")).\n\
+         variable_value(\"xs\",array,(1,value,3)).\n\
+         output_array(\"xs\",0,(1,2)).\n\
+         #show val(\"X_1\",V) : val(\"X_1\",V).\n\
+         #show.\n\
+         solve(satisfy).\n"
+            .to_string()
+    );
+    assert_eq!(show_atom("p(X,\"X\",XY)", "x"), "p(x,\"X\",XY)");
+}
+#[test]
+fn test_output_array() {
+    let mut state = State::default();
+    let config = Config::default();
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "array [1..2] of var int: a :: output_array([S,{1,2},{}]) = [X,Y];",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"a\",array(2,int)).\n\
+         variable_value(\"a\",array,(0,var,\"X\")).\n\
+         variable_value(\"a\",array,(1,var,\"Y\")).\n\
+         output_array(\"a\",0,(var,\"S\")).\n\
+         output_array(\"a\",1,(1,2)).\n\
+         output_array(\"a\",2,(1,0)).\n"
+            .to_string()
+    );
+    for stmt in &[
+        "array [1..2] of var int: a :: output_array([{1,3}]) = [X,Y];",
+        "array [1..2] of var int: a :: output_array(1) = [X,Y];",
+        "array [1..2] of var int: a :: output_array = [X,Y];",
+    ] {
+        assert!(write_fz_stmt(&mut res, stmt, &mut state, &config).is_err());
+    }
+    let err = write_fz_stmt(
+        &mut res,
+        "array [1..2] of var int: b :: output_array([{-170141183460469231731687303715884105727,\
+         170141183460469231731687303715884105727}]) = [X,Y];",
+        &mut State::default(),
+        &config,
+    )
+    .unwrap_err();
+    assert!(err.to_string().starts_with("Unsupported index set 0"));
+}
+#[test]
+fn test_parameter_output() {
+    let mut state = State::default();
+    let config = Config::default();
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "array [1..2] of int: a :: output_array([1..2]) = [1,2];",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    write_fz_stmt(
+        &mut res,
+        "bool: b :: output_var = true;",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "parameter_value(\"a\",array,(0,value,1)).\n\
+         parameter_value(\"a\",array,(1,value,2)).\n\
+         output_array(\"a\",0,(1,2)).\n\
+         output_fixed(\"a\").\n\
+         parameter_value(\"b\",value,true).\n\
+         output_var(\"b\").\n\
+         output_fixed(\"b\").\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_typed_constraints() {
+    let mut state = State::default();
+    let config = Config {
+        typed_constraints: true,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &[
+        "array [1..2] of int: cs = [2,3];",
+        "var 1..3: x;",
+        "var 1..3: y;",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "constraint int_lin_le(cs,[x,y],{1});",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "constraint(c1,\"int_lin_le\").\n\
+         constraint_type_at(c1,0,par(array(2,int))).\n\
+         constraint_value(c1,0,var,\"cs\").\n\
+         constraint_type_at(c1,1,var(array(2,int))).\n\
+         constraint_value(c1,1,array,(0,var,\"x\")).\n\
+         constraint_value(c1,1,array,(1,var,\"y\")).\n\
+         constraint_type_at(c1,2,par(set_of_int)).\n\
+         constraint_value(c1,2,set,(value,1)).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_inline_parameters() {
+    let mut state = State::default();
+    let config = Config {
+        inline_parameters: true,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &[
+        "array [1..2] of int: cs = [2,3];",
+        "int: b = 4;",
+        "set of int: s = 1..3;",
+        "var 1..3: x;",
+        "var 1..3: y;",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    let mut res = Vec::new();
+    write_fz_stmt(&mut res, "var int: z = b;", &mut state, &config).unwrap();
+    write_fz_stmt(
+        &mut res,
+        "array [1..2] of var int: zs = [b,x];",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    write_fz_stmt(
+        &mut res,
+        "constraint int_lin_le(cs,[x,y],b);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    write_fz_stmt(&mut res, "constraint set_in(x,s);", &mut state, &config).unwrap();
+    write_fz_stmt(
+        &mut res,
+        "constraint int_lin_eq([b,b],[x,y],b);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"z\",int).\n\
+         variable_value(\"z\",value,4).\n\
+         variable_type(\"zs\",array(2,int)).\n\
+         variable_value(\"zs\",array,(0,value,4)).\n\
+         variable_value(\"zs\",array,(1,var,\"x\")).\n\
+         constraint(c1,\"int_lin_le\").\n\
+         constraint_value(c1,0,array,(0,value,2)).\n\
+         constraint_value(c1,0,array,(1,value,3)).\n\
+         constraint_value(c1,1,array,(0,var,\"x\")).\n\
+         constraint_value(c1,1,array,(1,var,\"y\")).\n\
+         constraint_value(c1,2,value,4).\n\
+         constraint(c2,\"set_in\").\n\
+         constraint_value(c2,0,var,\"x\").\n\
+         constraint_value(c2,1,range,(value,1,value,3)).\n\
+         constraint(c3,\"int_lin_eq\").\n\
+         constraint_value(c3,0,array,(0,value,4)).\n\
+         constraint_value(c3,0,array,(1,value,4)).\n\
+         constraint_value(c3,1,array,(0,var,\"x\")).\n\
+         constraint_value(c3,1,array,(1,var,\"y\")).\n\
+         constraint_value(c3,2,value,4).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_expand_arrays() {
+    let mut state = State::default();
+    let config = Config {
+        expand_arrays: true,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &[
+        "array [1..2] of int: cs = [2,3];",
+        "var 1..3: x;",
+        "var 1..3: y;",
+        "array [1..2] of var int: xs = [x,y];",
+        "array [1..2] of var int: ys = [x,3];",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    let mut res = Vec::new();
+    write_fz_stmt(
+        &mut res,
+        "constraint int_lin_le(cs,xs,10);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    write_fz_stmt(
+        &mut res,
+        "constraint all_different_int(ys);",
+        &mut state,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "constraint(c1,\"int_lin_le\").\n\
+         constraint_value(c1,0,var,\"cs\").\n\
+         constraint_value(c1,1,array,(0,var,\"x\")).\n\
+         constraint_value(c1,1,array,(1,var,\"y\")).\n\
+         constraint_value(c1,2,value,10).\n\
+         constraint(c2,\"all_different_int\").\n\
+         constraint_value(c2,0,array,(0,var,\"x\")).\n\
+         constraint_value(c2,0,array,(1,value,3)).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_aliases() {
+    let mut state = State::default();
+    let config = Config {
+        resolve_aliases: true,
+        show: Some("val(X,V)".to_string()),
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &[
+        "var 1..3: x;",
+        "var int: y :: output_var = x;",
+        "var 1..2: z = y;",
+        "array [1..2] of var int: zs :: output_array([1..2]) = [z,x];",
+        "constraint int_le(z,y);",
+        "solve minimize z;",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"x\",int,range,(value,1,value,3)).\n\
+         variable_type(\"y\",int).\n\
+         output_var(\"y\").\n\
+         #show val(\"y\",V) : val(\"x\",V).\n\
+         alias(\"y\",\"x\").\n\
+         variable_type(\"z\",int,range,(value,1,value,2)).\n\
+         variable_value(\"z\",var,\"x\").\n\
+         variable_type(\"zs\",array(2,int)).\n\
+         variable_value(\"zs\",array,(0,var,\"z\")).\n\
+         variable_value(\"zs\",array,(1,var,\"x\")).\n\
+         output_array(\"zs\",0,(1,2)).\n\
+         #show val(\"z\",V) : val(\"z\",V).\n\
+         #show val(\"x\",V) : val(\"x\",V).\n\
+         constraint(c1,\"int_le\").\n\
+         constraint_value(c1,0,var,\"z\").\n\
+         constraint_value(c1,1,var,\"x\").\n\
+         #show.\n\
+         solve(minimize,var,\"z\").\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_alias_domains() {
+    let mut state = State::default();
+    let config = Config {
+        resolve_aliases: true,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &[
+        "var 1..3: x;",
+        "var 1..3: y = x;",
+        "var 1..2: z = y;",
+        "var 0..5: w = z;",
+        "var {1,2,3}: v = y;",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"x\",int,range,(value,1,value,3)).\n\
+         variable_type(\"y\",int,range,(value,1,value,3)).\n\
+         alias(\"y\",\"x\").\n\
+         variable_type(\"z\",int,range,(value,1,value,2)).\n\
+         variable_value(\"z\",var,\"x\").\n\
+         variable_type(\"w\",int,range,(value,0,value,5)).\n\
+         alias(\"w\",\"z\").\n\
+         variable_type(\"v\",int,set,(value,1)).\n\
+         variable_type(\"v\",int,set,(value,2)).\n\
+         variable_type(\"v\",int,set,(value,3)).\n\
+         alias(\"v\",\"x\").\n"
+    );
+}
+#[test]
+fn test_domains() {
+    let mut state = State::default();
+    let config = Config {
+        domain_threshold: Some(3),
+        set_threshold: Some(2),
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &[
+        "var 1..3: x;",
+        "var 1..4: y;",
+        "var {5,1,5}: z;",
+        "constraint set_in(x,{1,3});",
+        "constraint set_in(y,1..3);",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"x\",int,range,(value,1,value,3)).\n\
+         variable_domain(\"x\",1).\n\
+         variable_domain(\"x\",2).\n\
+         variable_domain(\"x\",3).\n\
+         variable_type(\"y\",int,range,(value,1,value,4)).\n\
+         variable_type(\"z\",int,set,(value,5)).\n\
+         variable_type(\"z\",int,set,(value,1)).\n\
+         variable_type(\"z\",int,set,(value,5)).\n\
+         variable_domain(\"z\",1).\n\
+         variable_domain(\"z\",5).\n\
+         constraint(c1,\"set_in\").\n\
+         constraint_value(c1,0,var,\"x\").\n\
+         constraint_value(c1,1,set,(value,1)).\n\
+         constraint_value(c1,1,set,(value,3)).\n\
+         constraint_set_element(c1,1,1).\n\
+         constraint_set_element(c1,1,3).\n\
+         constraint(c2,\"set_in\").\n\
+         constraint_value(c2,0,var,\"y\").\n\
+         constraint_value(c2,1,range,(value,1,value,3)).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_domain_checks() {
+    let mut state = State::default();
+    let config = Config::default();
+    let mut res = Vec::new();
+    for (stmt, msg) in &[
+        ("var 5..1: y;", "Empty domain 5..1 of variable y"),
+        (
+            "var 1..3: x = 7;",
+            "Value 7 of variable x is outside of its domain 1..3",
+        ),
+        (
+            "var 0.0..1.0: f = 1.5;",
+            "Value 1.5 of variable f is outside of its domain 0.0..1.0",
+        ),
+        (
+            "var set of 1..3: s = 2..4;",
+            "Value 4 of variable s is outside of its domain 1..3",
+        ),
+        (
+            "array [1..2] of var 1..3: xs = [1,5];",
+            "Value 5 of variable xs element 1 is outside of its domain 1..3",
+        ),
+    ] {
+        let err = write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap_err();
+        assert_eq!(err.to_string(), *msg);
+    }
+    // values of parameters are only known if they are inlined
+    write_fz_stmt(&mut res, "int: a = 4;", &mut state, &config).unwrap();
+    write_fz_stmt(&mut res, "var {1,3}: x = a;", &mut state, &config).unwrap();
+    let config = Config {
+        inline_parameters: true,
+        ..Default::default()
+    };
+    let mut state = State::default();
+    write_fz_stmt(&mut res, "int: a = 4;", &mut state, &config).unwrap();
+    let err = write_fz_stmt(&mut res, "var {1,3}: x = a;", &mut state, &config).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Value 4 of variable x is outside of its domain {1,3}"
+    );
+    let config = Config {
+        lenient: true,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    write_fz_stmt(&mut res, "var 1..3: x = 7;", &mut state, &config).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "variable_type(\"x\",int,range,(value,1,value,3)).\n\
+         variable_value(\"x\",value,7).\n"
+            .to_string()
+    );
+}
+#[test]
+fn test_identifier_checks() {
+    let mut state = State::default();
+    let config = Config {
+        check_identifiers: true,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &["set of int: s = 1..2;", "var 1..3: x;"] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    for (stmt, msg) in &[
+        ("var 1..3: x;", "Duplicate declaration of x"),
+        ("int: x = 1;", "Duplicate declaration of x"),
+        ("var int: y = z;", "Undefined identifier z in variable y"),
+        (
+            "array [1..2] of var int: ys = [x,z];",
+            "Undefined identifier z in variable ys",
+        ),
+        (
+            "array [1..2] of var int: ys :: output_array([t]) = [x,x];",
+            "Undefined identifier t in variable ys",
+        ),
+        (
+            "constraint int_le(x,z);",
+            "Undefined identifier z in constraint int_le argument 1",
+        ),
+        ("solve minimize z;", "Undefined identifier z in solve item"),
+    ] {
+        let err = write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap_err();
+        assert_eq!(err.to_string(), *msg);
+    }
+    for stmt in &[
+        "array [1..2] of var int: ys :: output_array([s]) = [x,x];",
+        "constraint set_in(x,s);",
+        "solve minimize x;",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+}
+#[test]
+fn test_signature_checks() {
+    let mut state = State::default();
+    let config = Config {
+        check_signatures: true,
+        ..Default::default()
+    };
+    let mut res = Vec::new();
+    for stmt in &[
+        "predicate my_pred(array [int] of var int: xs,int: k);",
+        "array [1..2] of int: cs = [2,3];",
+        "var 1..3: x;",
+        "var 1..3: y;",
+        "var bool: b;",
+        "constraint int_lin_le(cs,[x,y],10);",
+        "constraint int_le_reif(x,3,b);",
+        "constraint my_pred([x,y],2);",
+        "constraint array_bool_or([],b);",
+    ] {
+        write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+    }
+    for (stmt, msg) in &[
+        (
+            "constraint int_lin_le([x,y],cs,10);",
+            "Argument 0 of constraint c5 (int_lin_le) has type array [int] of var int, \
+             expected array [int] of int",
+        ),
+        (
+            "constraint int_le(x,y,b);",
+            "Constraint c6 (int_le) expects 2 arguments, found 3",
+        ),
+        (
+            "constraint my_pred([x,y],y);",
+            "Argument 1 of constraint c7 (my_pred) has type var int, expected int",
+        ),
+        (
+            "constraint my_constraint(x);",
+            "Unknown constraint my_constraint in constraint c8",
+        ),
+    ] {
+        let err = write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap_err();
+        assert_eq!(err.to_string(), *msg);
+    }
+}
+#[test]
+fn test_supported_constraints() {
+    let mut supported = SupportedConstraints::default();
+    supported.add_encoding(
+        "le(C) :- constraint(C,\"int_le\").\n\
+         sum(C) :- constraint(C, \"int_lin_le\"), constraint_value(C,0,var,\"x\").\n\
+         :- my_constraint(C,\"int_ne\").",
+    );
+    supported.add_list("% list\nint_eq\n\n\"bool_clause\"\n");
+    for name in &["int_le", "int_lin_le", "int_eq", "bool_clause"] {
+        assert!(supported.contains(name));
+    }
+    assert!(!supported.contains("int_ne"));
+    let mut report = SupportReport::default();
+    for (line, stmt) in [
+        "var 1..3: x;",
+        "constraint int_le(x,2);",
+        "constraint int_ne(x,2);",
+        "constraint int_times(x,x,x);",
+        "constraint int_ne(x,1);",
+    ]
+    .iter()
+    .enumerate()
+    {
+        let (stmt, _) = parse_fz_stmt(stmt).unwrap();
+        report.check(&stmt, line + 1, &supported);
+    }
+    let mut res = Vec::new();
+    report.write(&mut res).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "int_ne: 2 occurrences, first in line 3\n\
+         int_times: 1 occurrence, first in line 4\n"
+    );
+}
+#[test]
+fn test_stats() {
+    let mut stats = Stats::default();
+    for stmt in &[
+        "predicate my_pred(var int: x);",
+        "array [1..3] of int: cs = [1,2,3];",
+        "int: k :: output_var = 3;",
+        "var 1..3: x :: output_var;",
+        "var 1..4: y;",
+        "var {1,5,5}: z;",
+        "var bool: b;",
+        "array [1..3] of var int: xs :: output_array([1..3]) = [x,y,z];",
+        "constraint int_lin_le(cs,xs,10);",
+        "constraint int_le(x,y);",
+        "constraint int_le(y,z);",
+        "constraint int_le(x,y) :: domain;",
+        "solve maximize x;",
+    ] {
+        let (stmt, par_annos) = parse_fz_stmt(stmt).unwrap();
+        stats.add(&stmt, &par_annos);
+    }
+    let mut res = Vec::new();
+    stats.write_text(&mut res).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "predicates: 1\n\
+         parameters: 2\n  array of int: 1\n  int: 1\n\
+         variables: 5\n  array of int: 1\n  bool: 1\n  int range: 2\n  int set: 1\n\
+         domain sizes:\n  <= 2: 2\n  <= 4: 2\n\
+         array lengths:\n  3: 2\n\
+         constraints: 4\n  int_le: 3\n  int_lin_le: 1\n\
+         duplicate constraints: 1\n\
+         output variables: 2\n\
+         output arrays: 1\n\
+         objective: maximize int\n"
+    );
+    let mut res = Vec::new();
+    stats.write_json(&mut res).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "{\"predicates\":1,\
+         \"parameters\":{\"total\":2,\"by_type\":{\"array of int\":1,\"int\":1}},\
+         \"variables\":{\"total\":5,\"by_type\":\
+         {\"array of int\":1,\"bool\":1,\"int range\":2,\"int set\":1}},\
+         \"domain_sizes\":{\"2\":2,\"4\":2},\
+         \"array_lengths\":{\"3\":2},\
+         \"constraints\":{\"total\":4,\"by_type\":{\"int_le\":3,\"int_lin_le\":1}},\
+         \"duplicate_constraints\":1,\
+         \"output_vars\":2,\"output_arrays\":1,\"objective\":\"maximize int\"}\n"
+    );
+}
+#[test]
+fn test_stats_extreme_domain() {
+    let mut stats = Stats::default();
+    let (stmt, par_annos) = parse_fz_stmt(
+        "var -170141183460469231731687303715884105727..170141183460469231731687303715884105727: x;",
+    )
+    .unwrap();
+    stats.add(&stmt, &par_annos);
+    assert_eq!(stats.domain_sizes.get(&u128::MAX), Some(&1));
+}
+#[test]
+fn test_features_extreme_domain() {
+    let mut features = Features::default();
+    let (stmt, _) = parse_fz_stmt(
+        "var -170141183460469231731687303715884105727..170141183460469231731687303715884105727: x;",
+    )
+    .unwrap();
+    features.add(&stmt);
+    let values: std::collections::HashMap<_, _> = features.values().into_iter().collect();
+    assert_eq!(values["domain_size_max"], 2f64.powi(128));
+}
+#[test]
+fn test_features() {
+    let mut features = Features::default();
+    for stmt in &[
+        "array [1..2] of int: cs = [1,2];",
+        "var 1..3: x;",
+        "var {1,5,5}: y;",
+        "var bool: b;",
+        "array [1..2] of var int: xs = [x,y];",
+        "constraint int_lin_le(cs,xs,10);",
+        "constraint int_le_reif(x,y,b);",
+        "constraint all_different_int(xs);",
+        "constraint bool_clause([b],[]);",
+        "solve :: int_search(xs,input_order,indomain_min,complete) minimize x;",
+    ] {
+        let (stmt, _) = parse_fz_stmt(stmt).unwrap();
+        features.add(&stmt);
+    }
+    let values: std::collections::HashMap<_, _> = features.values().into_iter().collect();
+    assert_eq!(values["variables"], 3.0);
+    assert_eq!(values["variable_arrays"], 1.0);
+    assert_eq!(values["constraints"], 4.0);
+    assert_eq!(values["domain_size_min"], 2.0);
+    assert_eq!(values["domain_size_max"], 3.0);
+    assert_eq!(values["domain_size_mean"], 2.5);
+    assert_eq!(values["int_constraint_ratio"], 0.5);
+    assert_eq!(values["global_constraint_ratio"], 0.25);
+    assert_eq!(values["linear_constraint_ratio"], 0.25);
+    assert_eq!(values["reified_constraint_ratio"], 0.25);
+    assert_eq!(values["variable_degree_max"], 3.0);
+    assert_eq!(values["variable_degree_min"], 2.0);
+    assert_eq!(values["constraint_degree_max"], 3.0);
+    assert_eq!(values["constraint_degree_min"], 1.0);
+    assert_eq!(values["objective"], 1.0);
+    assert_eq!(values["objective_degree"], 3.0);
+    assert_eq!(values["objective_domain_size"], 3.0);
+    assert_eq!(values["search_annotations"], 1.0);
+    let mut res = Vec::new();
+    features.write_csv(&mut res).unwrap();
+    let res = String::from_utf8(res).unwrap();
+    let lines: Vec<&str> = res.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("parameters,variables,bool_variables,"));
+    assert!(lines[1].starts_with("1,3,1,2,0,0,1,4,2,3,2.5,0.5,"));
+    let mut res = Vec::new();
+    features.write_json(&mut res).unwrap();
+    assert!(std::str::from_utf8(&res)
+        .unwrap()
+        .starts_with("{\"parameters\":1,\"variables\":3,"));
+}
+#[test]
+fn test_incidence() {
+    let config = Config {
+        incidence: true,
+        ..Default::default()
+    };
+    let mut state = State::default();
+    let mut graph = IncidenceGraph::default();
+    let mut res = Vec::new();
+    for stmt in &[
+        "array [1..2] of int: cs = [1,2];",
+        "var 1..3: x;",
+        "var 1..3: y;",
+        "var bool: b;",
+        "array [1..2] of var int: xs = [x,y];",
+        "constraint int_lin_le(cs,xs,10);",
+        "constraint int_le_reif(x,x,b);",
+    ] {
+        let (stmt, par_annos) = parse_fz_stmt(stmt).unwrap();
+        graph.add(&stmt);
+        write_stmt(&mut res, stmt, &par_annos, &mut state, &config).unwrap();
+    }
+    let res = std::str::from_utf8(&res).unwrap();
+    assert!(res.contains("constraint_variable(c1,\"x\").\nconstraint_variable(c1,\"y\").\n"));
+    assert!(res.contains("constraint_variable(c2,\"x\").\nconstraint_variable(c2,\"b\").\n"));
+    assert_eq!(res.matches("constraint_variable(c2,").count(), 2);
+    let mut res = Vec::new();
+    graph.write_dot(&mut res).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "graph incidence {\n  node [shape=box];\n  \
+         c1 [label=\"int_lin_le\"];\n  c2 [label=\"int_le_reif\"];\n  \
+         node [shape=ellipse];\n  \
+         v0 [label=\"x\"];\n  v1 [label=\"y\"];\n  v2 [label=\"b\"];\n  \
+         c1 -- v0;\n  c1 -- v1;\n  c2 -- v0;\n  c2 -- v2;\n}\n"
+    );
+    let mut res = Vec::new();
+    graph.write_graphml(&mut res).unwrap();
+    let res = std::str::from_utf8(&res).unwrap();
+    assert!(res.contains("<data key=\"label\">int_le_reif</data>"));
+    assert!(res.contains("<edge source=\"c2\" target=\"v2\"/>"));
+}
+#[test]
+fn test_components() {
+    let mut components = Components::default();
+    for stmt in &[
+        "int: k = 3;",
+        "var 1..3: x;",
+        "var 1..3: y;",
+        "var 1..3: z;",
+        "var 1..3: w = z;",
+        "array [1..2] of var int: xs = [x,y];",
+        "constraint int_le(x,y);",
+        "constraint int_ne(z,k);",
+        "constraint bool_clause([],[]);",
+        "solve maximize w;",
+    ] {
+        let (stmt, _) = parse_fz_stmt(stmt).unwrap();
+        components.add(&stmt);
+    }
+    let assignment = components.assign();
+    assert_eq!(assignment.count, 3);
+    assert_eq!(assignment.objective, Some(2));
+    assert_eq!(assignment.variable("xs"), Some(1));
+    assert_eq!(assignment.constraint(3), Some(3));
+    let mut res = Vec::new();
+    assignment
+        .write_facts(&mut res, &Config::default())
+        .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "component(1,var,\"x\").\ncomponent(1,var,\"y\").\n\
+         component(2,var,\"z\").\ncomponent(2,var,\"w\").\n\
+         component(1,var,\"xs\").\n\
+         component(1,constraint,c1).\ncomponent(2,constraint,c2).\n\
+         component(3,constraint,c3).\n"
+    );
+}
+#[test]
+fn test_slice() {
+    let lines = [
+        "predicate my_le(var int: a, var int: b);",
+        "int: k = 3;",
+        "array [1..2] of int: cs = [1,2];",
+        "var 1..3: x;",
+        "var 1..3: y;",
+        "var 1..3: z;",
+        "array [1..2] of var int: xs = [x,y];",
+        "constraint int_lin_le(cs,xs,4);",
+        "constraint my_le(z,k);",
+        "solve :: int_search(xs,input_order,indomain_min,complete) maximize z;",
+    ];
+    let stmts: Vec<FzStmt> = lines.iter().map(|l| parse_fz_stmt(l).unwrap().0).collect();
+    let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    let slice = Slice::new(&stmts, &["x".to_string()]).unwrap();
+    let mut res = Vec::new();
+    slice.write_fzn(&mut res, &stmts, &lines).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "array [1..2] of int: cs = [1,2];\n\
+         var 1..3: x;\nvar 1..3: y;\n\
+         array [1..2] of var int: xs = [x,y];\n\
+         constraint int_lin_le(cs,xs,4);\n\
+         solve satisfy;\n"
+    );
+    let slice = Slice::new(&stmts, &["z".to_string()]).unwrap();
+    let mut res = Vec::new();
+    slice.write_fzn(&mut res, &stmts, &lines).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "predicate my_le(var int: a, var int: b);\n\
+         int: k = 3;\nvar 1..3: z;\n\
+         constraint my_le(z,k);\n\
+         solve maximize z;\n"
+    );
+    assert!(Slice::new(&stmts, &["q".to_string()]).is_err());
+}
+#[test]
+fn test_elimination() {
+    let mut stmts = vec![];
+    let mut par_annos = vec![];
+    for stmt in &[
+        "int: k = 3;",
+        "int: unused = 4;",
+        "int: shown :: output_var = 5;",
+        "var 1..3: x;",
+        "var 1..3: y :: var_is_introduced;",
+        "var 1..3: t :: var_is_introduced;",
+        "var 1..3: u :: var_is_introduced;",
+        "array [1..2] of var int: zs :: var_is_introduced = [t,u];",
+        "array [1..2] of var int: ys :: var_is_introduced = [y,y];",
+        "var 1..2: narrow :: var_is_introduced = x;",
+        "var 0..4: wide :: var_is_introduced = x;",
+        "array [1..2] of var 1..2: narrows :: var_is_introduced = [x,y];",
+        "array [1..2] of var 1..3: wides :: var_is_introduced = [x,y];",
+        "constraint int_le(x,k);",
+        "solve :: int_search(ys,input_order,indomain_min,complete) satisfy;",
+    ] {
+        let (stmt, annos) = parse_fz_stmt(stmt).unwrap();
+        stmts.push(stmt);
+        par_annos.push(annos);
+    }
+    let elimination = Elimination::new(&stmts, &par_annos);
+    let removed: Vec<&str> = elimination
+        .removed()
+        .iter()
+        .map(|r| r.id.as_str())
+        .collect();
+    assert_eq!(removed, ["unused", "t", "u", "zs", "wide", "wides"]);
+    assert!(elimination.removes(1));
+    assert!(!elimination.removes(4));
+    let mut res = Vec::new();
+    elimination.write_report(&mut res).unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
-        "parameter_value(\"h\",array,(0,set,(value,42))).\n\
-         parameter_value(\"h\",array,(0,set,(value,17))).\n\
-         parameter_value(\"h\",array,(1,range,(value,1,value,5))).\n\
-         parameter_value(\"h\",array,(2,empty_set)).\n"
-            .to_string()
+        "Removed unused parameter unused in line 2\n\
+         Removed unused variable t in line 6\n\
+         Removed unused variable u in line 7\n\
+         Removed unused variable zs in line 8\n\
+         Removed unused variable wide in line 11\n\
+         Removed unused variable wides in line 13\n"
     );
 }
 #[test]
-fn test_constraints() {
-    let mut counter = 0;
-    let mut level = 0;
+fn test_duplicates() {
+    let stmts = [
+        "var 1..3: x;",
+        "var 1..3: y;",
+        "constraint set_in(x,{1,3});",
+        "constraint int_le(x,y);",
+        "constraint set_in(x,{3,1,3}) :: domain;",
+        "constraint int_le(y,x);",
+    ];
+    for (policy, expected) in &[
+        (DuplicatePolicy::Keep, 4),
+        (DuplicatePolicy::Report, 4),
+        (DuplicatePolicy::Remove, 3),
+    ] {
+        let config = Config {
+            duplicates: *policy,
+            ..Default::default()
+        };
+        let mut state = State::default();
+        let mut res = Vec::new();
+        for stmt in &stmts {
+            write_fz_stmt(&mut res, stmt, &mut state, &config).unwrap();
+        }
+        let res = std::str::from_utf8(&res).unwrap();
+        assert_eq!(res.matches("constraint(").count(), *expected);
+        assert_eq!(
+            res.contains("duplicate_of(c3,c1)."),
+            *policy == DuplicatePolicy::Report
+        );
+        assert!(res.matches("duplicate_of(").count() <= 1);
+        assert_eq!(
+            res.contains("constraint(c3,"),
+            *policy != DuplicatePolicy::Remove
+        );
+        assert!(res.contains("constraint(c4,"));
+    }
+}
+#[test]
+fn test_presolve() {
+    let lines = [
+        "int: k = 4;",
+        "var 1..10: x :: output_var;",
+        "var int: y;",
+        "var {1,3,5,7}: z;",
+        "var 2..2: w;",
+        "var bool: b;",
+        "var int: u;",
+        "constraint int_eq(y,3);",
+        "constraint int_le(x,k);",
+        "constraint int_le(z,4);",
+        "constraint bool_eq(b,true);",
+        "constraint int_le(w,x);",
+        "constraint int_lin_le([1,1],[x,z],9);",
+        "constraint int_le(u,5);",
+        "solve minimize x;",
+    ];
+    let mut stmts: Vec<FzStmt> = lines.iter().map(|l| parse_fz_stmt(l).unwrap().0).collect();
+    let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    let presolve = Presolve::new(&stmts);
     let mut res = Vec::new();
-    write_fz_stmt(
+    presolve.write_fzn(&mut res, &stmts, &lines).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "int: k = 4;\n\
+         var 2..4: x :: output_var;\n\
+         var 3..3: y = 3;\n\
+         var {1,3}: z;\n\
+         var 2..2: w = 2;\n\
+         var bool: b = true;\n\
+         var int: u;\n\
+         constraint int_lin_le([1,1],[x,z],9);\n\
+         constraint int_le(u,5);\n\
+         solve minimize x;\n"
+    );
+    let mut res = Vec::new();
+    presolve.write_log(&mut res).unwrap();
+    let log = String::from_utf8(res).unwrap();
+    assert!(log.starts_with("Tightened the domain of x to 2..4\nFixed y to 3\n"));
+    assert!(log.ends_with("Removed satisfied constraint int_le in line 12\n"));
+    assert!(presolve.removes(7));
+    assert!(!presolve.removes(13));
+    presolve.apply(2, &mut stmts[2]);
+    let mut res = Vec::new();
+    let mut state = State::default();
+    write_stmt(
         &mut res,
-        "constraint bla(42,42.1,true,a,[42,17,X],{X,34},37..48,[{42,17},17..34,{X,Y}]);",
-        &mut counter,
-        &mut level,
+        stmts[2].clone(),
+        &[],
+        &mut state,
+        &Config::default(),
     )
     .unwrap();
     assert_eq!(
         std::str::from_utf8(&res).unwrap(),
-        "constraint(c1,\"bla\").\n\
-         constraint_value(c1,0,value,42).\n\
-         constraint_value(c1,1,value,\"42.1\").\n\
-         constraint_value(c1,2,value,true).\n\
-         constraint_value(c1,3,var,\"a\").\n\
-         constraint_value(c1,4,array,(0,value,42)).\n\
-         constraint_value(c1,4,array,(1,value,17)).\n\
-         constraint_value(c1,4,array,(2,var,\"X\")).\n\
-         constraint_value(c1,5,set,(var,\"X\")).\n\
-         constraint_value(c1,5,set,(value,34)).\n\
-         constraint_value(c1,6,range,(value,37,value,48)).\n\
-         constraint_value(c1,7,array,(0,set,(value,42))).\n\
-         constraint_value(c1,7,array,(0,set,(value,17))).\n\
-         constraint_value(c1,7,array,(1,range,(value,17,value,34))).\n\
-         constraint_value(c1,7,array,(2,set,(var,\"X\"))).\n\
-         constraint_value(c1,7,array,(2,set,(var,\"Y\"))).\n"
-            .to_string()
+        "variable_type(\"y\",int,range,(value,3,value,3)).\n\
+         variable_value(\"y\",value,3).\n"
+    );
+
+    let stmts: Vec<FzStmt> = ["var 1..3: x;", "constraint int_eq(x,5);"]
+        .iter()
+        .map(|l| parse_fz_stmt(l).unwrap().0)
+        .collect();
+    let presolve = Presolve::new(&stmts);
+    assert!(!presolve.removes(1));
+    let mut res = Vec::new();
+    presolve.write_log(&mut res).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&res).unwrap(),
+        "The model is infeasible, empty domain of x; nothing changed\n"
     );
 }
+/// Converts a single FlatZinc statement
 pub fn write_fz_stmt(
-    mut out: impl Write,
+    out: &mut impl Sink,
     input: &str,
-    constraint_counter: &mut usize,
-    level: &mut i32,
+    state: &mut State,
+    config: &Config,
 ) -> Result<()> {
-    match fz_statement::<VerboseError<&str>>(&input) {
-        Ok((_rest, stmt)) => {
-            match stmt {
-                FzStmt::Comment(s) => {
-                    writeln!(out, "%{}", s)?;
-                }
-                FzStmt::Predicate(pred) => {
-                    if *level > 1 {
-                        warn!("Statements in wrong order.");
-                    }
-                    write_predicate(out, &pred)?;
-                }
-                FzStmt::Parameter(p) => {
-                    if *level > 2 {
-                        warn!("Statements in wrong order.");
-                    } else {
-                        *level = 2;
-                    }
-                    write_par_decl_item(out, &p)?;
-                }
-                FzStmt::Variable(d) => {
-                    if *level > 3 {
-                        warn!("Statements in wrong order.");
-                    } else {
-                        *level = 3;
-                    }
-                    write_var_decl_item(out, &d)?;
-                }
-                FzStmt::Constraint(c) => {
-                    if *level > 4 {
-                        warn!("Statements in wrong order.");
-                    } else {
-                        *level = 4;
-                    }
-                    *constraint_counter += 1;
-                    write_constraint(out, &c, *constraint_counter)?;
-                }
-                FzStmt::SolveItem(i) => {
-                    if *level > 4 {
-                        return Err(FlatZincError::MultipleSolveItems.into());
-                    }
-                    *level = 5;
-                    write_solve_item(out, &i)?;
-                }
-            }
-            Ok(())
-        }
+    let (stmt, par_annos) = parse_fz_stmt(input)?;
+    write_stmt(out, stmt, &par_annos, state, config)
+}
+
+/// Parses a single FlatZinc statement,
+/// returns it together with the annotations of a parameter declaration
+pub fn parse_fz_stmt(input: &str) -> Result<(FzStmt, Vec<Annotation>)> {
+    let (input, par_annos) = match split_par_annotations(input) {
+        Some((input, annos)) => (Cow::Owned(input), annos),
+        None => (Cow::Borrowed(input), vec![]),
+    };
+    let input = input.as_ref();
+    match fz_statement::<VerboseError<&str>>(input) {
+        Ok((_rest, stmt)) => Ok((stmt, par_annos)),
         Err(Err::Error(e)) | Err(Err::Failure(e)) => {
-            let bla = convert_error(&input, e);
+            let bla = convert_error(input, e);
             Err(FlatZincError::ParseError { msg: bla }.into())
         }
         Err(e) => Err(FlatZincError::ParseError {
@@ -393,31 +1937,184 @@ pub fn write_fz_stmt(
     }
 }
 
-fn write_predicate(mut buf: impl Write, predicate: &PredicateItem) -> Result<()> {
-    writeln!(buf, "predicate({}).", identifier(&predicate.id))?;
+/// Converts a parsed FlatZinc statement
+pub fn write_stmt(
+    out: &mut impl Sink,
+    stmt: FzStmt,
+    par_annos: &[Annotation],
+    state: &mut State,
+    config: &Config,
+) -> Result<()> {
+    int_range::check_stmt(&stmt, par_annos, config)?;
+    if config.check_identifiers {
+        references::check_stmt(&stmt, par_annos, &state.symbols, config)?;
+    }
+    match stmt {
+        FzStmt::Comment(s) => {
+            write_comment(out.comment_writer(Category::from_level(state.level))?, &s)?;
+        }
+        FzStmt::Predicate(pred) => {
+            if state.level > 1 {
+                warn!("Statements in wrong order.");
+            }
+            state.symbols.declare_predicate(&pred);
+            write_predicate(out.writer(Category::Predicates)?, &pred, config)?;
+        }
+        FzStmt::Parameter(p) => {
+            if state.level > 2 {
+                warn!("Statements in wrong order.");
+            } else {
+                state.level = 2;
+            }
+            state.symbols.declare_parameter(&p, config);
+            write_par_decl_item(out.writer(Category::Parameters)?, &p, config)?;
+            write_par_output(out, &p, par_annos, &mut state.symbols, config)?;
+        }
+        FzStmt::Variable(mut d) => {
+            if state.level > 3 {
+                warn!("Statements in wrong order.");
+            } else {
+                state.level = 3;
+            }
+            domain::check_var_decl_item(&d, &state.symbols, config)?;
+            let representative = if config.resolve_aliases {
+                alias::resolve_var_decl_item(&mut d, &mut state.symbols)
+            } else {
+                None
+            };
+            if config.inline_parameters {
+                inline::inline_var_decl_item(&mut d, &state.symbols);
+            }
+            state.symbols.declare_variable(&d, config);
+            write_var_decl_item(out, &d, &mut state.symbols, config)?;
+            if let Some(representative) = representative {
+                writeln!(
+                    out.writer(Category::Variables)?,
+                    "alias({},{}).",
+                    identifier(var_decl_id(&d), config),
+                    identifier(&representative, config)
+                )?;
+            }
+        }
+        FzStmt::Constraint(mut c) => {
+            if state.level > 4 {
+                warn!("Statements in wrong order.");
+            } else {
+                state.level = 4;
+            }
+            if config.resolve_aliases {
+                alias::resolve_constraint(&mut c, &state.symbols);
+            }
+            if config.expand_arrays {
+                inline::expand_constraint(&mut c, &state.symbols);
+            }
+            if config.inline_parameters {
+                inline::inline_constraint(&mut c, &state.symbols);
+            }
+            state.constraint_counter += 1;
+            if config.check_signatures {
+                signatures::check_constraint(&c, state.constraint_counter, &state.symbols, config)?;
+            }
+            let first = match config.duplicates {
+                DuplicatePolicy::Keep => None,
+                _ => state.duplicates.check(&c, state.constraint_counter),
+            };
+            if first.is_some() && config.duplicates == DuplicatePolicy::Remove {
+                return Ok(());
+            }
+            let mut buf = out.writer(Category::Constraints)?;
+            write_constraint(
+                &mut buf,
+                &c,
+                state.constraint_counter,
+                &state.symbols,
+                config,
+            )?;
+            if let Some(first) = first {
+                writeln!(
+                    buf,
+                    "duplicate_of(c{},c{}).",
+                    state.constraint_counter, first
+                )?;
+            }
+        }
+        FzStmt::SolveItem(mut i) => {
+            if state.level > 4 {
+                return Err(FlatZincError::MultipleSolveItems.into());
+            }
+            state.level = 5;
+            if config.resolve_aliases {
+                alias::resolve_solve_item(&mut i, &state.symbols);
+            }
+            if config.show.is_some() {
+                writeln!(out.writer(Category::Outputs)?, "#show.")?;
+            }
+            write_solve_item(out.writer(Category::Solve)?, &i, config)?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits the annotations off a parameter declaration like `int: a :: output_var = 1;`,
+/// since the parser only supports annotations on variable declarations
+fn split_par_annotations(input: &str) -> Option<(String, Vec<Annotation>)> {
+    let eq = input.find('=')?;
+    let colons = input[..eq].find("::")?;
+    let par_type = &input[..input.find(':')?];
+    if par_type
+        .split_whitespace()
+        .any(|t| matches!(t, "var" | "predicate" | "constraint" | "solve"))
+    {
+        return None;
+    }
+    let annos = &input[colons + 2..eq];
+    match var_decl_item::<VerboseError<&str>>(&format!("var bool: x :: {};", annos)) {
+        Ok((rest, VarDeclItem::Bool { annos, .. })) if rest.trim().is_empty() => {
+            Some((format!("{}{}", &input[..colons], &input[eq..]), annos))
+        }
+        _ => None,
+    }
+}
+fn write_comment(mut buf: impl Write, comment: &str) -> Result<()> {
+    let mut lines = comment.lines().peekable();
+    if lines.peek().is_none() {
+        writeln!(buf, "%")?;
+    }
+    for line in lines {
+        // `%*` would open a clingo block comment
+        if line.starts_with('*') {
+            writeln!(buf, "% {}", line)?;
+        } else {
+            writeln!(buf, "%{}", line)?;
+        }
+    }
+    Ok(())
+}
+fn write_predicate(mut buf: impl Write, predicate: &PredicateItem, config: &Config) -> Result<()> {
+    writeln!(buf, "predicate({}).", identifier(&predicate.id, config))?;
     for (pos, p) in predicate.parameters.iter().enumerate() {
         match p {
             (PredParType::Basic(par_type), id) => {
-                for element in basic_pred_par_type(&par_type) {
+                for element in basic_pred_par_type(par_type, config) {
                     writeln!(
                         buf,
                         "predicate_parameter({},{},{},{}).",
-                        identifier(&predicate.id),
+                        identifier(&predicate.id, config),
                         pos,
-                        identifier(id),
+                        identifier(id, config),
                         element
                     )?;
                 }
             }
             (PredParType::Array { ix, par_type }, id) => {
-                for element in basic_pred_par_type(&par_type) {
+                for element in basic_pred_par_type(par_type, config) {
                     writeln!(
                         buf,
                         "predicate_parameter({},{},{},{}).",
-                        identifier(&predicate.id),
+                        identifier(&predicate.id, config),
                         pos,
-                        identifier(id),
-                        array_type(&pred_index(&ix), &element)
+                        identifier(id, config),
+                        array_type(&pred_index(ix, config), &element)
                     )?;
                 }
             }
@@ -425,13 +2122,13 @@ fn write_predicate(mut buf: impl Write, predicate: &PredicateItem) -> Result<()>
     }
     Ok(())
 }
-fn write_par_decl_item(mut buf: impl Write, item: &ParDeclItem) -> Result<()> {
+fn write_par_decl_item(mut buf: impl Write, item: &ParDeclItem, config: &Config) -> Result<()> {
     match item {
         ParDeclItem::Bool { id, bool } => {
             writeln!(
                 buf,
                 "parameter_value({},value,{}).",
-                identifier(id),
+                identifier(id, config),
                 bool_literal(*bool)
             )?;
         }
@@ -439,74 +2136,79 @@ fn write_par_decl_item(mut buf: impl Write, item: &ParDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "parameter_value({},value,{}).",
-                identifier(id),
-                int_literal(int)
+                identifier(id, config),
+                int_literal(int, config)
             )?;
         }
         ParDeclItem::Float { id, float } => {
             writeln!(
                 buf,
                 "parameter_value({},value,{}).",
-                identifier(id),
-                float_literal(*float)
+                identifier(id, config),
+                float_literal(*float, config)
             )?;
         }
         ParDeclItem::SetOfInt {
             id,
             set_literal: sl,
         } => {
-            let set = dec_set_literal(sl);
+            let set = dec_set_literal(sl, config);
             for element in set {
-                writeln!(buf, "parameter_value({},{}).", identifier(id), element)?;
+                writeln!(
+                    buf,
+                    "parameter_value({},{}).",
+                    identifier(id, config),
+                    element
+                )?;
             }
         }
-        ParDeclItem::ArrayOfBool { ix, id, v } => {
+        ParDeclItem::ArrayOfBool { ix: _, id, v } => {
             for (pos, e) in v.iter().enumerate() {
                 writeln!(
                     buf,
                     "parameter_value({},array,({},value,{})).",
-                    identifier(id),
+                    identifier(id, config),
                     pos,
                     bool_literal(*e)
                 )?;
             }
         }
-        ParDeclItem::ArrayOfInt { ix, id, v } => {
+        ParDeclItem::ArrayOfInt { ix: _, id, v } => {
             for (pos, e) in v.iter().enumerate() {
                 writeln!(
                     buf,
                     "parameter_value({},array,({},value,{})).",
-                    identifier(id),
+                    identifier(id, config),
                     pos,
-                    int_literal(e)
+                    int_literal(e, config)
                 )?;
             }
         }
-        ParDeclItem::ArrayOfFloat { ix, id, v } => {
+        ParDeclItem::ArrayOfFloat { ix: _, id, v } => {
             for (pos, e) in v.iter().enumerate() {
                 writeln!(
                     buf,
                     "parameter_value({},array,({},value,{})).",
-                    identifier(id),
+                    identifier(id, config),
                     pos,
-                    float_literal(*e)
+                    float_literal(*e, config)
                 )?;
             }
         }
-        ParDeclItem::ArrayOfSet { ix, id, v } => {
+        ParDeclItem::ArrayOfSet { ix: _, id, v } => {
             // writeln!(
             //     buf,
             //     "variable_type({},{}).",
-            //     identifier(id),
+            //     identifier(id, config),
             //     array_type(&index(ix), "set")
             // )?;
             for (pos, e) in v.iter().enumerate() {
-                let set = dec_set_literal(e);
+                let set = dec_set_literal(e, config);
                 for element in set {
                     writeln!(
                         buf,
                         "parameter_value({},array,({},{})).",
-                        identifier(id),
+                        identifier(id, config),
                         pos,
                         element
                     )?;
@@ -516,31 +2218,70 @@ fn write_par_decl_item(mut buf: impl Write, item: &ParDeclItem) -> Result<()> {
     }
     Ok(())
 }
-fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
+/// Writes the output annotations of a parameter,
+/// the additional fact `output_fixed(Id)` marks the value as fixed
+fn write_par_output(
+    out: &mut impl Sink,
+    item: &ParDeclItem,
+    annos: &[Annotation],
+    symbols: &mut SymbolTable,
+    config: &Config,
+) -> Result<()> {
+    let id = match item {
+        ParDeclItem::Bool { id, .. }
+        | ParDeclItem::Int { id, .. }
+        | ParDeclItem::Float { id, .. }
+        | ParDeclItem::SetOfInt { id, .. }
+        | ParDeclItem::ArrayOfBool { id, .. }
+        | ParDeclItem::ArrayOfInt { id, .. }
+        | ParDeclItem::ArrayOfFloat { id, .. }
+        | ParDeclItem::ArrayOfSet { id, .. } => id,
+    };
+    write_output_var(out, id, annos, symbols, config)?;
+    write_output_array(out, id, annos, &[], symbols, config)?;
+    if annos
+        .iter()
+        .any(|a| a.id == "output_var" || a.id == "output_array")
+    {
+        writeln!(
+            out.writer(Category::Outputs)?,
+            "output_fixed({}).",
+            identifier(id, config)
+        )?;
+    }
+    Ok(())
+}
+fn write_var_decl_item(
+    out: &mut impl Sink,
+    item: &VarDeclItem,
+    symbols: &mut SymbolTable,
+    config: &Config,
+) -> Result<()> {
+    let buf = out.writer(Category::Variables)?;
     match item {
         VarDeclItem::Bool { id, expr, annos } => {
-            writeln!(buf, "variable_type({},bool).", identifier(id))?;
+            writeln!(buf, "variable_type({},bool).", identifier(id, config))?;
             if let Some(expr) = expr {
                 writeln!(
                     buf,
                     "variable_value({},{}).",
-                    identifier(id),
-                    bool_expr(expr)
+                    identifier(id, config),
+                    bool_expr(expr, config)
                 )?;
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::Int { id, expr, annos } => {
-            writeln!(buf, "variable_type({},int).", identifier(id))?;
+            writeln!(buf, "variable_type({},int).", identifier(id, config))?;
             if let Some(expr) = expr {
                 writeln!(
                     buf,
                     "variable_value({},{}).",
-                    identifier(id),
-                    int_expr(expr)
+                    identifier(id, config),
+                    int_expr(expr, config)
                 )?;
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::IntInRange {
             id,
@@ -552,18 +2293,21 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                int_in_range(lb, ub)
+                identifier(id, config),
+                int_in_range(lb, ub, config)
             )?;
+            if let Some(values) = enumerate_range(*lb, *ub, config.domain_threshold) {
+                write_domain(&mut *buf, id, &values, config)?;
+            }
             if let Some(expr) = expr {
                 writeln!(
                     buf,
                     "variable_value({},{}).",
-                    identifier(id),
-                    int_expr(expr)
+                    identifier(id, config),
+                    int_expr(expr, config)
                 )?;
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::IntInSet {
             id,
@@ -571,30 +2315,38 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             expr,
             annos,
         } => {
-            for element in int_in_set(set) {
-                writeln!(buf, "variable_type({},{}).", identifier(id), element,)?;
+            for element in int_in_set(set, config) {
+                writeln!(
+                    buf,
+                    "variable_type({},{}).",
+                    identifier(id, config),
+                    element,
+                )?;
+            }
+            if let Some(values) = enumerate_set(set, config.domain_threshold) {
+                write_domain(&mut *buf, id, &values, config)?;
             }
             if let Some(expr) = expr {
                 writeln!(
                     buf,
                     "variable_value({},{}).",
-                    identifier(id),
-                    int_expr(expr)
+                    identifier(id, config),
+                    int_expr(expr, config)
                 )?;
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::Float { id, expr, annos } => {
-            writeln!(buf, "variable_type({},float).", identifier(id))?;
+            writeln!(buf, "variable_type({},float).", identifier(id, config))?;
             if let Some(expr) = expr {
                 writeln!(
                     buf,
                     "variable_value({},{}).",
-                    identifier(id),
-                    float_expr(expr)
+                    identifier(id, config),
+                    float_expr(expr, config)
                 )?;
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::BoundedFloat {
             id,
@@ -606,28 +2358,33 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                bounded_float(*lb, *ub)
+                identifier(id, config),
+                bounded_float(*lb, *ub, config)
             )?;
             if let Some(expr) = expr {
                 writeln!(
                     buf,
                     "variable_value({},{}).",
-                    identifier(id),
-                    float_expr(expr)
+                    identifier(id, config),
+                    float_expr(expr, config)
                 )?;
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::SetOfInt { id, annos, expr } => {
-            writeln!(buf, "variable_type({},set_of_int).", identifier(id))?;
+            writeln!(buf, "variable_type({},set_of_int).", identifier(id, config))?;
             if let Some(expr) = expr {
-                let set = dec_set_expr(expr);
+                let set = dec_set_expr(expr, config);
                 for element in set {
-                    writeln!(buf, "variable_value({},{}).", identifier(id), element)?;
+                    writeln!(
+                        buf,
+                        "variable_value({},{}).",
+                        identifier(id, config),
+                        element
+                    )?;
                 }
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::SubSetOfIntRange {
             id,
@@ -639,16 +2396,21 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                subset_of_int_range(lb, ub),
+                identifier(id, config),
+                subset_of_int_range(lb, ub, config),
             )?;
             if let Some(expr) = expr {
-                let set = dec_set_expr(expr);
+                let set = dec_set_expr(expr, config);
                 for element in set {
-                    writeln!(buf, "variable_value({},{}).", identifier(id), element)?;
+                    writeln!(
+                        buf,
+                        "variable_value({},{}).",
+                        identifier(id, config),
+                        element
+                    )?;
                 }
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::SubSetOfIntSet {
             id,
@@ -660,17 +2422,22 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                 writeln!(
                     buf,
                     "variable_type({},set_of_int,set,(value,{})).",
-                    identifier(id),
-                    element,
+                    identifier(id, config),
+                    int_literal(element, config),
                 )?;
             }
             if let Some(expr) = expr {
-                let set = dec_set_expr(expr);
+                let set = dec_set_expr(expr, config);
                 for element in set {
-                    writeln!(buf, "variable_value({},{}).", identifier(id), element)?;
+                    writeln!(
+                        buf,
+                        "variable_value({},{}).",
+                        identifier(id, config),
+                        element
+                    )?;
                 }
             }
-            write_output_var(buf, id, annos)?;
+            write_output_var(out, id, annos, symbols, config)?;
         }
         VarDeclItem::ArrayOfBool {
             id,
@@ -681,8 +2448,8 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                array_type(&index(ix), "bool")
+                identifier(id, config),
+                array_type(&index(ix, config), "bool")
             )?;
             match array_expr {
                 Some(ArrayOfBoolExpr::Array(v)) => {
@@ -690,9 +2457,9 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                         writeln!(
                             buf,
                             "variable_value({},array,({},{})).",
-                            identifier(id),
+                            identifier(id, config),
                             pos,
-                            bool_expr(e)
+                            bool_expr(e, config)
                         )?;
                     }
                 }
@@ -700,13 +2467,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &bool_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfInt {
             id,
@@ -717,8 +2491,8 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                array_type(&index(ix), "int"),
+                identifier(id, config),
+                array_type(&index(ix, config), "int"),
             )?;
             match array_expr {
                 Some(ArrayOfIntExpr::Array(v)) => {
@@ -726,9 +2500,9 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                         writeln!(
                             buf,
                             "variable_value({},array,({},{})).",
-                            identifier(id),
+                            identifier(id, config),
                             pos,
-                            int_expr(e)
+                            int_expr(e, config)
                         )?;
                     }
                 }
@@ -736,13 +2510,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &int_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfIntInRange {
             id,
@@ -755,8 +2536,8 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                array_type(&index(ix), &int_in_range(lb, ub)),
+                identifier(id, config),
+                array_type(&index(ix, config), &int_in_range(lb, ub, config)),
             )?;
             match array_expr {
                 Some(ArrayOfIntExpr::Array(v)) => {
@@ -764,9 +2545,9 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                         writeln!(
                             buf,
                             "variable_value({},array,({},{})).",
-                            identifier(id),
+                            identifier(id, config),
                             pos,
-                            int_expr(e)
+                            int_expr(e, config)
                         )?;
                     }
                 }
@@ -774,13 +2555,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &int_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfIntInSet {
             id,
@@ -789,12 +2577,12 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             array_expr,
             annos,
         } => {
-            for element in int_in_set(set) {
+            for element in int_in_set(set, config) {
                 writeln!(
                     buf,
                     "variable_type({},{}).",
-                    identifier(id),
-                    array_type(&index(ix), &element)
+                    identifier(id, config),
+                    array_type(&index(ix, config), &element)
                 )?;
             }
             match array_expr {
@@ -803,9 +2591,9 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                         writeln!(
                             buf,
                             "variable_value({},array,({},{})).",
-                            identifier(id),
+                            identifier(id, config),
                             pos,
-                            int_expr(e)
+                            int_expr(e, config)
                         )?;
                     }
                 }
@@ -813,13 +2601,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &int_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfFloat {
             id,
@@ -830,8 +2625,8 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                array_type(&index(ix), "float"),
+                identifier(id, config),
+                array_type(&index(ix, config), "float"),
             )?;
             match array_expr {
                 Some(ArrayOfFloatExpr::Array(v)) => {
@@ -839,9 +2634,9 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                         writeln!(
                             buf,
                             "variable_value({},array,({},{})).",
-                            identifier(id),
+                            identifier(id, config),
                             pos,
-                            float_expr(e)
+                            float_expr(e, config)
                         )?;
                     }
                 }
@@ -849,13 +2644,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &float_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfBoundedFloat {
             id,
@@ -868,8 +2670,8 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                array_type(&index(ix), &bounded_float(*lb, *ub)),
+                identifier(id, config),
+                array_type(&index(ix, config), &bounded_float(*lb, *ub, config)),
             )?;
             match array_expr {
                 Some(ArrayOfFloatExpr::Array(v)) => {
@@ -877,9 +2679,9 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                         writeln!(
                             buf,
                             "variable_value({},array,({},{})).",
-                            identifier(id),
+                            identifier(id, config),
                             pos,
-                            float_expr(e)
+                            float_expr(e, config)
                         )?;
                     }
                 }
@@ -887,13 +2689,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &float_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfSet {
             id,
@@ -904,18 +2713,18 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                array_type(&index(ix), "set"),
+                identifier(id, config),
+                array_type(&index(ix, config), "set"),
             )?;
             match array_expr {
                 Some(ArrayOfSetExpr::Array(v)) => {
                     for (pos, e) in v.iter().enumerate() {
-                        let set = dec_set_expr(e);
+                        let set = dec_set_expr(e, config);
                         for element in set {
                             writeln!(
                                 buf,
                                 "variable_value({},array,({},{})).",
-                                identifier(id),
+                                identifier(id, config),
                                 pos,
                                 element
                             )?;
@@ -926,13 +2735,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &set_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfSubSetOfIntRange {
             id,
@@ -945,18 +2761,18 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
             writeln!(
                 buf,
                 "variable_type({},{}).",
-                identifier(id),
-                array_type(&index(ix), &subset_of_int_range(lb, ub))
+                identifier(id, config),
+                array_type(&index(ix, config), &subset_of_int_range(lb, ub, config))
             )?;
             match array_expr {
                 Some(ArrayOfSetExpr::Array(v)) => {
                     for (pos, e) in v.iter().enumerate() {
-                        let set = dec_set_expr(e);
+                        let set = dec_set_expr(e, config);
                         for element in set {
                             writeln!(
                                 buf,
                                 "variable_value({},array,({},{})).",
-                                identifier(id),
+                                identifier(id, config),
                                 pos,
                                 element
                             )?;
@@ -967,13 +2783,20 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &set_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
         VarDeclItem::ArrayOfSubSetOfIntSet {
             id,
@@ -986,18 +2809,21 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                 writeln!(
                     buf,
                     "variable_type({},{}).",
-                    identifier(id),
-                    array_type(&index(ix), &format!("set_of_int,set,(value,{})", element)),
+                    identifier(id, config),
+                    array_type(
+                        &index(ix, config),
+                        &format!("set_of_int,set,(value,{})", int_literal(element, config))
+                    ),
                 )?;
             }
             match array_expr {
                 Some(ArrayOfSetExpr::Array(v)) => {
                     for (pos, se) in v.iter().enumerate() {
-                        for e in dec_set_expr(se) {
+                        for e in dec_set_expr(se, config) {
                             writeln!(
                                 buf,
                                 "variable_value({},array,({},{})).",
-                                identifier(id),
+                                identifier(id, config),
                                 pos,
                                 e
                             )?;
@@ -1008,83 +2834,182 @@ fn write_var_decl_item(mut buf: impl Write, item: &VarDeclItem) -> Result<()> {
                     writeln!(
                         buf,
                         "variable_value({},value,{}).",
-                        identifier(id),
-                        identifier(id2)
+                        identifier(id, config),
+                        identifier(id2, config)
                     )?;
                 }
                 None => {}
             }
-            write_output_array(buf, id, annos)?;
+            write_output_array(
+                out,
+                id,
+                annos,
+                &set_array_identifiers(array_expr),
+                symbols,
+                config,
+            )?;
         }
     }
     Ok(())
 }
-fn basic_var_type(t: &BasicVarType) -> Vec<String> {
+fn write_domain(mut buf: impl Write, id: &str, values: &[i128], config: &Config) -> Result<()> {
+    for v in values {
+        writeln!(
+            buf,
+            "variable_domain({},{}).",
+            identifier(id, config),
+            int_literal(v, config)
+        )?;
+    }
+    Ok(())
+}
+/// Returns the values of the range `lb..ub`, if there are at most `threshold` of them
+fn enumerate_range(lb: i128, ub: i128, threshold: Option<u64>) -> Option<Vec<i128>> {
+    let threshold = threshold?;
+    if ub < lb {
+        return Some(vec![]);
+    }
+    match ub.checked_sub(lb) {
+        Some(d) if d < threshold as i128 => Some((lb..=ub).collect()),
+        _ => None,
+    }
+}
+/// Returns the distinct values of a set, if there are at most `threshold` of them
+fn enumerate_set(set: &[i128], threshold: Option<u64>) -> Option<Vec<i128>> {
+    let threshold = threshold?;
+    let mut values = set.to_vec();
+    values.sort_unstable();
+    values.dedup();
+    if values.len() as u64 <= threshold {
+        Some(values)
+    } else {
+        None
+    }
+}
+/// Returns the values of a set literal of integers, if there are at most `threshold` of them
+fn enumerate_set_literal_expr(e: &SetLiteralExpr, threshold: Option<u64>) -> Option<Vec<i128>> {
+    match e {
+        SetLiteralExpr::IntInRange(IntExpr::Int(lb), IntExpr::Int(ub)) => {
+            enumerate_range(*lb, *ub, threshold)
+        }
+        SetLiteralExpr::SetInts(v) => {
+            let set = v
+                .iter()
+                .map(|e| match e {
+                    IntExpr::Int(i) => Some(*i),
+                    IntExpr::VarParIdentifier(_) => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            enumerate_set(&set, threshold)
+        }
+        _ => None,
+    }
+}
+fn var_decl_id(item: &VarDeclItem) -> &str {
+    match item {
+        VarDeclItem::Bool { id, .. }
+        | VarDeclItem::Int { id, .. }
+        | VarDeclItem::IntInRange { id, .. }
+        | VarDeclItem::IntInSet { id, .. }
+        | VarDeclItem::Float { id, .. }
+        | VarDeclItem::BoundedFloat { id, .. }
+        | VarDeclItem::SetOfInt { id, .. }
+        | VarDeclItem::SubSetOfIntRange { id, .. }
+        | VarDeclItem::SubSetOfIntSet { id, .. }
+        | VarDeclItem::ArrayOfBool { id, .. }
+        | VarDeclItem::ArrayOfInt { id, .. }
+        | VarDeclItem::ArrayOfIntInRange { id, .. }
+        | VarDeclItem::ArrayOfIntInSet { id, .. }
+        | VarDeclItem::ArrayOfFloat { id, .. }
+        | VarDeclItem::ArrayOfBoundedFloat { id, .. }
+        | VarDeclItem::ArrayOfSet { id, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntRange { id, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntSet { id, .. } => id,
+    }
+}
+fn basic_var_type(t: &BasicVarType, config: &Config) -> Vec<String> {
     match t {
         BasicVarType::BasicType(BasicType::Bool) => vec!["bool".to_string()],
         BasicVarType::BasicType(BasicType::Int) => vec!["int".to_string()],
-        BasicVarType::IntInRange(lb, ub) => vec![int_in_range(lb, ub)],
-        BasicVarType::IntInSet(set) => int_in_set(set),
+        BasicVarType::IntInRange(lb, ub) => vec![int_in_range(lb, ub, config)],
+        BasicVarType::IntInSet(set) => int_in_set(set, config),
         BasicVarType::BasicType(BasicType::Float) => vec!["float".to_string()],
-        BasicVarType::BoundedFloat(lb, ub) => vec![bounded_float(*lb, *ub)],
-        BasicVarType::SubSetOfIntRange(lb, ub) => vec![subset_of_int_range(lb, ub)],
-        BasicVarType::SubSetOfIntSet(set) => subset_of_int_set(set),
+        BasicVarType::BoundedFloat(lb, ub) => vec![bounded_float(*lb, *ub, config)],
+        BasicVarType::SubSetOfIntRange(lb, ub) => vec![subset_of_int_range(lb, ub, config)],
+        BasicVarType::SubSetOfIntSet(set) => subset_of_int_set(set, config),
     }
 }
-fn int_in_range(lb: &i128, ub: &i128) -> String {
-    format!("int,range,(value,{},value,{})", lb, ub)
+fn int_in_range(lb: &i128, ub: &i128, config: &Config) -> String {
+    format!(
+        "int,range,(value,{},value,{})",
+        int_literal(lb, config),
+        int_literal(ub, config)
+    )
 }
-fn int_in_set(set: &[i128]) -> Vec<String> {
+fn int_in_set(set: &[i128], config: &Config) -> Vec<String> {
     let mut ret = vec![];
     for integer in set {
-        ret.push(format!("int,set,(value,{})", integer))
+        ret.push(format!("int,set,(value,{})", int_literal(integer, config)))
     }
     ret
 }
-fn float_in_set(set: &[f64]) -> Vec<String> {
+fn float_in_set(set: &[f64], config: &Config) -> Vec<String> {
     let mut ret = vec![];
     for float in set {
-        ret.push(format!("float_in_set({})", float))
+        ret.push(format!("float_in_set({})", float_literal(*float, config)))
     }
     ret
 }
-fn bounded_float(lb: f64, ub: f64) -> String {
+fn bounded_float(lb: f64, ub: f64, config: &Config) -> String {
     format!(
         "float,(bounds,value,{},value,{})",
-        float_literal(lb),
-        float_literal(ub)
+        float_literal(lb, config),
+        float_literal(ub, config)
     )
 }
-fn subset_of_int_range(lb: &i128, ub: &i128) -> String {
+fn subset_of_int_range(lb: &i128, ub: &i128, config: &Config) -> String {
     format!(
         "set_of_int,range,(value,{},value,{})",
-        int_literal(lb),
-        int_literal(ub)
+        int_literal(lb, config),
+        int_literal(ub, config)
     )
 }
-fn subset_of_int_set(set: &[i128]) -> Vec<String> {
+fn subset_of_int_set(set: &[i128], config: &Config) -> Vec<String> {
     let mut ret = vec![];
     for i in set {
-        ret.push(format!("set_of_int,set,(value,{})", int_literal(i)))
+        ret.push(format!("set_of_int,set,(value,{})", int_literal(i, config)))
     }
     ret
 }
-fn write_constraint(mut buf: impl Write, c: &ConstraintItem, i: usize) -> Result<()> {
-    writeln!(buf, "constraint(c{},{}).", i, identifier(&c.id))?;
+fn write_constraint(
+    mut buf: impl Write,
+    c: &ConstraintItem,
+    i: usize,
+    symbols: &SymbolTable,
+    config: &Config,
+) -> Result<()> {
+    writeln!(buf, "constraint(c{},{}).", i, identifier(&c.id, config))?;
     for (cpos, ce) in c.exprs.iter().enumerate() {
+        if config.typed_constraints {
+            match argument_type(ce, symbols) {
+                Some(t) => writeln!(buf, "constraint_type_at(c{},{},{}).", i, cpos, t)?,
+                None => warn!(
+                    "Unable to determine the type of argument {} of constraint {}.",
+                    cpos, c.id
+                ),
+            }
+        }
         match ce {
             Expr::VarParIdentifier(id) => {
-                // writeln!(buf, "constraint_type_at(c{},{},var_par).", i, cpos)?;
                 writeln!(
                     buf,
                     "constraint_value(c{},{},var,{}).",
                     i,
                     cpos,
-                    identifier(id)
+                    identifier(id, config)
                 )?;
             }
             Expr::Bool(e) => {
-                // writeln!(buf, "constraint_type_at(c{},{},bool).", i, cpos)?;
                 writeln!(
                     buf,
                     "constraint_value(c{},{},value,{}).",
@@ -1094,34 +3019,41 @@ fn write_constraint(mut buf: impl Write, c: &ConstraintItem, i: usize) -> Result
                 )?;
             }
             Expr::Int(e) => {
-                // writeln!(buf, "constraint_type_at(c{},{},int).", i, cpos)?;
                 writeln!(
                     buf,
                     "constraint_value(c{},{},value,{}).",
                     i,
                     cpos,
-                    int_literal(e)
+                    int_literal(e, config)
                 )?;
             }
             Expr::Float(e) => {
-                // writeln!(buf, "constraint_type_at(c{},{},float).", i, cpos)?;
                 writeln!(
                     buf,
                     "constraint_value(c{},{},value,{}).",
                     i,
                     cpos,
-                    float_literal(*e)
+                    float_literal(*e, config)
                 )?;
             }
             Expr::Set(e) => {
-                // writeln!(buf, "constraint_type_at(c{},{},set).", i, cpos)?;
-                let set = dec_set_literal_expr(e);
+                let set = dec_set_literal_expr(e, config);
                 for element in set {
                     writeln!(buf, "constraint_value(c{},{},{}).", i, cpos, element)?;
                 }
+                if let Some(values) = enumerate_set_literal_expr(e, config.set_threshold) {
+                    for v in values {
+                        writeln!(
+                            buf,
+                            "constraint_set_element(c{},{},{}).",
+                            i,
+                            cpos,
+                            int_literal(&v, config)
+                        )?;
+                    }
+                }
             }
             Expr::ArrayOfBool(v) => {
-                // writeln!(buf, "constraint_type_at(c{},{},array).", i, cpos)?;
                 for (apos, ae) in v.iter().enumerate() {
                     writeln!(
                         buf,
@@ -1129,12 +3061,11 @@ fn write_constraint(mut buf: impl Write, c: &ConstraintItem, i: usize) -> Result
                         i,
                         cpos,
                         apos,
-                        bool_expr(&ae)
+                        bool_expr(ae, config)
                     )?;
                 }
             }
             Expr::ArrayOfInt(v) => {
-                // writeln!(buf, "constraint_type_at(c{},{},array).", i, cpos)?;
                 for (apos, ae) in v.iter().enumerate() {
                     writeln!(
                         buf,
@@ -1142,12 +3073,11 @@ fn write_constraint(mut buf: impl Write, c: &ConstraintItem, i: usize) -> Result
                         i,
                         cpos,
                         apos,
-                        int_expr(&ae)
+                        int_expr(ae, config)
                     )?;
                 }
             }
             Expr::ArrayOfFloat(v) => {
-                // writeln!(buf, "constraint_type_at(c{},{},array).", i, cpos,)?;
                 for (apos, ae) in v.iter().enumerate() {
                     writeln!(
                         buf,
@@ -1155,14 +3085,13 @@ fn write_constraint(mut buf: impl Write, c: &ConstraintItem, i: usize) -> Result
                         i,
                         cpos,
                         apos,
-                        float_expr(&ae)
+                        float_expr(ae, config)
                     )?;
                 }
             }
             Expr::ArrayOfSet(v) => {
-                // writeln!(buf, "constraint_type_at(c{},{},array_of_set).", i, cpos)?;
                 for (apos, ae) in v.iter().enumerate() {
-                    let set = dec_set_expr(ae);
+                    let set = dec_set_expr(ae, config);
                     for element in set {
                         writeln!(
                             buf,
@@ -1174,24 +3103,97 @@ fn write_constraint(mut buf: impl Write, c: &ConstraintItem, i: usize) -> Result
             }
         }
     }
+    if config.incidence {
+        for id in symbols.constraint_variables(c) {
+            writeln!(
+                buf,
+                "constraint_variable(c{},{}).",
+                i,
+                identifier(id, config)
+            )?;
+        }
+    }
     Ok(())
 }
-fn write_solve_item(mut buf: impl Write, i: &SolveItem) -> Result<()> {
+/// Returns the type of a constraint argument as term, e.g. `var(int)` or `par(array(2,int))`,
+/// where literals are parameters and arrays are variables if any element is a variable
+fn argument_type(e: &Expr, symbols: &SymbolTable) -> Option<String> {
+    let par = |t: &str| Some(format!("par({})", t));
+    let array = |len: usize, base_type: BaseType| {
+        let mut ids = vec![];
+        incidence::argument_ids(e, &mut ids);
+        let kind = if ids.iter().any(|id| {
+            symbols
+                .get(id)
+                .is_some_and(|s| s.kind == SymbolKind::Variable)
+        }) {
+            SymbolKind::Variable
+        } else {
+            SymbolKind::Parameter
+        };
+        Some(format!(
+            "{}(array({},{}))",
+            kind.name(),
+            len,
+            base_type.name()
+        ))
+    };
+    match e {
+        Expr::VarParIdentifier(id) => symbols.get(id).map(|s| s.type_term()),
+        Expr::Bool(_) => par("bool"),
+        Expr::Int(_) => par("int"),
+        Expr::Float(_) => par("float"),
+        Expr::Set(SetLiteralExpr::IntInRange(_, _)) | Expr::Set(SetLiteralExpr::SetInts(_)) => {
+            par("set_of_int")
+        }
+        Expr::Set(SetLiteralExpr::BoundedFloat(_, _)) | Expr::Set(SetLiteralExpr::SetFloats(_)) => {
+            par("set_of_float")
+        }
+        // a list of identifiers is parsed as array of bool,
+        // so the element type is derived from the declarations
+        Expr::ArrayOfBool(v) => {
+            let mut base_type = BaseType::Bool;
+            for (pos, e) in v.iter().enumerate() {
+                let t = match e {
+                    BoolExpr::Bool(_) => BaseType::Bool,
+                    BoolExpr::VarParIdentifier(id) => match symbols.get(id) {
+                        Some(Symbol {
+                            base_type,
+                            len: None,
+                            ..
+                        }) => *base_type,
+                        _ => return None,
+                    },
+                };
+                if pos == 0 {
+                    base_type = t;
+                } else if t != base_type {
+                    return None;
+                }
+            }
+            array(v.len(), base_type)
+        }
+        Expr::ArrayOfInt(v) => array(v.len(), BaseType::Int),
+        Expr::ArrayOfFloat(v) => array(v.len(), BaseType::Float),
+        Expr::ArrayOfSet(v) => array(v.len(), BaseType::SetOfInt),
+    }
+}
+fn write_solve_item(mut buf: impl Write, i: &SolveItem, config: &Config) -> Result<()> {
     match &i.goal {
         Goal::Satisfy => {
             writeln!(buf, "solve(satisfy).")?;
         }
         Goal::OptimizeBool(ot, e) => {
-            writeln!(buf, "solve({},{}).", opt_type(ot), bool_expr(&e))?;
+            writeln!(buf, "solve({},{}).", opt_type(ot), bool_expr(e, config))?;
         }
         Goal::OptimizeInt(ot, e) => {
-            writeln!(buf, "solve({},{}).", opt_type(ot), int_expr(&e))?;
+            writeln!(buf, "solve({},{}).", opt_type(ot), int_expr(e, config))?;
         }
         Goal::OptimizeFloat(ot, e) => {
-            writeln!(buf, "solve({},{}).", opt_type(ot), float_expr(&e))?;
+            writeln!(buf, "solve({},{}).", opt_type(ot), float_expr(e, config))?;
         }
         Goal::OptimizeSet(ot, e) => {
-            let set = dec_set_expr(e);
+            let set = dec_set_expr(e, config);
             for element in set {
                 writeln!(buf, "solve({},{}).", opt_type(ot), element)?;
             }
@@ -1207,17 +3209,17 @@ fn basic_par_type(t: &BasicParType) -> String {
         BasicParType::SetOfInt => "set_of_int".to_string(),
     }
 }
-fn basic_pred_par_type(t: &BasicPredParType) -> Vec<String> {
+fn basic_pred_par_type(t: &BasicPredParType, config: &Config) -> Vec<String> {
     match t {
         BasicPredParType::BasicParType(t) => vec![basic_par_type(t)],
-        BasicPredParType::BasicVarType(t) => basic_var_type(t),
+        BasicPredParType::BasicVarType(t) => basic_var_type(t, config),
         BasicPredParType::VarSetOfInt => vec!["set_of_int".to_string()],
-        BasicPredParType::BoundedFloat(lb, ub) => vec![bounded_float(*lb, *ub)],
-        BasicPredParType::IntInRange(lb, ub) => vec![int_in_range(lb, ub)],
-        BasicPredParType::IntInSet(set) => int_in_set(set),
-        BasicPredParType::FloatInSet(set) => float_in_set(set),
-        BasicPredParType::SubSetOfIntRange(lb, ub) => vec![subset_of_int_range(lb, ub)],
-        BasicPredParType::SubSetOfIntSet(set) => subset_of_int_set(set),
+        BasicPredParType::BoundedFloat(lb, ub) => vec![bounded_float(*lb, *ub, config)],
+        BasicPredParType::IntInRange(lb, ub) => vec![int_in_range(lb, ub, config)],
+        BasicPredParType::IntInSet(set) => int_in_set(set, config),
+        BasicPredParType::FloatInSet(set) => float_in_set(set, config),
+        BasicPredParType::SubSetOfIntRange(lb, ub) => vec![subset_of_int_range(lb, ub, config)],
+        BasicPredParType::SubSetOfIntSet(set) => subset_of_int_set(set, config),
     }
 }
 fn array_type(idx: &str, element_type: &str) -> String {
@@ -1229,22 +3231,50 @@ fn opt_type(opt_type: &OptimizationType) -> String {
         OptimizationType::Maximize => "maximize".to_string(),
     }
 }
-fn index(IndexSet(i): &IndexSet) -> String {
-    i.to_string()
+fn index(IndexSet(i): &IndexSet, config: &Config) -> String {
+    int_literal(i, config)
+}
+fn identifier(id: &str, config: &Config) -> String {
+    match config.identifiers {
+        IdentifierMode::Constant if is_constant(id) => id.to_string(),
+        _ => string(id),
+    }
+}
+/// Returns true if `id` is a valid clingo constant, i.e. `_*[a-z][A-Za-z0-9_']*`
+fn is_constant(id: &str) -> bool {
+    let name = id.trim_start_matches('_');
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_lowercase() => {}
+        _ => return false,
+    }
+    name != "not" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '\'')
 }
-fn identifier(id: &str) -> String {
-    format!("\"{}\"", id)
+/// Quotes and escapes `s` as a clingo string constant
+fn string(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => ret.push_str("\\\\"),
+            '"' => ret.push_str("\\\""),
+            '\n' => ret.push_str("\\n"),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
 }
-fn pred_index(is: &PredIndexSet) -> String {
+fn pred_index(is: &PredIndexSet, config: &Config) -> String {
     match is {
-        PredIndexSet::IndexSet(i) => i.to_string(),
+        PredIndexSet::IndexSet(i) => int_literal(i, config),
         PredIndexSet::Int => "int".to_string(),
     }
 }
-fn bool_expr(e: &BoolExpr) -> String {
+fn bool_expr(e: &BoolExpr, config: &Config) -> String {
     match e {
         BoolExpr::Bool(b) => format!("value,{}", bool_literal(*b)),
-        BoolExpr::VarParIdentifier(id) => format!("var,{}", identifier(id)),
+        BoolExpr::VarParIdentifier(id) => format!("var,{}", identifier(id, config)),
     }
 }
 fn bool_literal(b: bool) -> String {
@@ -1254,45 +3284,120 @@ fn bool_literal(b: bool) -> String {
         "false".to_string()
     }
 }
-fn int_expr(e: &IntExpr) -> String {
+fn int_expr(e: &IntExpr, config: &Config) -> String {
     match e {
-        IntExpr::Int(i) => format!("value,{}", int_literal(i)),
-        IntExpr::VarParIdentifier(id) => format!("var,{}", identifier(id)),
+        IntExpr::Int(i) => format!("value,{}", int_literal(i, config)),
+        IntExpr::VarParIdentifier(id) => format!("var,{}", identifier(id, config)),
     }
 }
-fn int_literal(i: &i128) -> String {
-    i.to_string()
+fn int_literal(i: &i128, config: &Config) -> String {
+    if config.int_range.0 <= *i && *i <= config.int_range.1 {
+        return i.to_string();
+    }
+    match config.overflow {
+        OverflowPolicy::Error | OverflowPolicy::Warn => i.to_string(),
+        OverflowPolicy::String => string(&i.to_string()),
+        OverflowPolicy::Split => {
+            // limbs in base 10^9, most significant first
+            let mut limbs = vec![];
+            let mut rest = i.unsigned_abs();
+            while rest > 0 {
+                limbs.push((rest % 1_000_000_000).to_string());
+                rest /= 1_000_000_000;
+            }
+            limbs.push(if *i < 0 { "-1" } else { "1" }.to_string());
+            limbs.reverse();
+            format!("big({})", limbs.join(","))
+        }
+    }
 }
-fn float_expr(e: &FloatExpr) -> String {
+fn float_expr(e: &FloatExpr, config: &Config) -> String {
     match e {
-        FloatExpr::Float(f) => format!("value,{}", float_literal(*f)),
-        FloatExpr::VarParIdentifier(id) => format!("var,{}", identifier(id)),
+        FloatExpr::Float(f) => format!("value,{}", float_literal(*f, config)),
+        FloatExpr::VarParIdentifier(id) => format!("var,{}", identifier(id, config)),
+    }
+}
+fn float_literal(f: f64, config: &Config) -> String {
+    match config.floats {
+        FloatMode::String => string(&f.to_string()),
+        FloatMode::Scaled(factor) => {
+            let exact = decimal_fraction(f).and_then(|(num, den)| {
+                let scaled = num.checked_mul(factor as i128)?;
+                if scaled % den == 0 {
+                    Some(scaled / den)
+                } else {
+                    None
+                }
+            });
+            match exact {
+                Some(i) => int_literal(&i, config),
+                None => {
+                    warn!("Precision loss when scaling float {} by {}.", f, factor);
+                    int_literal(&((f * factor as f64).round() as i128), config)
+                }
+            }
+        }
+        FloatMode::Rational => match decimal_fraction(f) {
+            Some((num, den)) => format!(
+                "rational({},{})",
+                int_literal(&num, config),
+                int_literal(&den, config)
+            ),
+            None => {
+                warn!(
+                    "Float {} can not be represented as rational, using a string.",
+                    f
+                );
+                string(&f.to_string())
+            }
+        },
     }
 }
-fn float_literal(f: f64) -> String {
-    format!("\"{}\"", f)
+/// Returns the reduced fraction `(numerator, denominator)` of the decimal representation of `f`
+pub(crate) fn decimal_fraction(f: f64) -> Option<(i128, i128)> {
+    let literal = f.to_string();
+    let (int_part, frac_part) = match literal.find('.') {
+        Some(pos) => (&literal[..pos], &literal[pos + 1..]),
+        None => (literal.as_str(), ""),
+    };
+    let den = 10i128.checked_pow(frac_part.len() as u32)?;
+    let num: i128 = format!("{}{}", int_part, frac_part).parse().ok()?;
+    let divisor = gcd(num.abs(), den);
+    Some((num / divisor, den / divisor))
 }
-fn dec_set_expr(e: &SetExpr) -> Vec<String> {
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        let t = b;
+        b = a % b;
+        a = t;
+    }
+    a
+}
+fn dec_set_expr(e: &SetExpr, config: &Config) -> Vec<String> {
     match e {
-        SetExpr::Set(sl) => dec_set_literal_expr(sl),
-        SetExpr::VarParIdentifier(id) => vec![format!("var,{}", identifier(id))],
+        SetExpr::Set(sl) => dec_set_literal_expr(sl, config),
+        SetExpr::VarParIdentifier(id) => vec![format!("var,{}", identifier(id, config))],
     }
 }
-fn dec_set_literal_expr(l: &SetLiteralExpr) -> Vec<String> {
+fn dec_set_literal_expr(l: &SetLiteralExpr, config: &Config) -> Vec<String> {
     let mut ret = Vec::new();
     match l {
-        SetLiteralExpr::BoundedFloat(f1, f2) => {
-            ret.push(format!("bounds,({},{})", float_expr(f1), float_expr(f2)))
-        }
-        SetLiteralExpr::IntInRange(i1, i2) => {
-            ret.push(format!("range,({},{})", int_expr(i1), int_expr(i2)))
-        }
+        SetLiteralExpr::BoundedFloat(f1, f2) => ret.push(format!(
+            "bounds,({},{})",
+            float_expr(f1, config),
+            float_expr(f2, config)
+        )),
+        SetLiteralExpr::IntInRange(i1, i2) => ret.push(format!(
+            "range,({},{})",
+            int_expr(i1, config),
+            int_expr(i2, config)
+        )),
         SetLiteralExpr::SetFloats(v) => {
             if v.is_empty() {
                 ret.push("empty_set".to_string());
             } else {
                 for f in v {
-                    ret.push(format!("set,({})", float_expr(f)));
+                    ret.push(format!("set,({})", float_expr(f, config)));
                 }
             }
         }
@@ -1301,28 +3406,32 @@ fn dec_set_literal_expr(l: &SetLiteralExpr) -> Vec<String> {
                 ret.push("empty_set".to_string());
             } else {
                 for i in v {
-                    ret.push(format!("set,({})", int_expr(i)));
+                    ret.push(format!("set,({})", int_expr(i, config)));
                 }
             }
         }
     }
     ret
 }
-fn dec_set_literal(l: &SetLiteral) -> Vec<String> {
+fn dec_set_literal(l: &SetLiteral, config: &Config) -> Vec<String> {
     let mut ret = Vec::new();
     match l {
         SetLiteral::BoundedFloat(f1, f2) => ret.push(format!(
             "bounds,(value,{},value,{})",
-            float_literal(*f1),
-            float_literal(*f2)
+            float_literal(*f1, config),
+            float_literal(*f2, config)
+        )),
+        SetLiteral::IntRange(i1, i2) => ret.push(format!(
+            "range,(value,{},value,{})",
+            int_literal(i1, config),
+            int_literal(i2, config)
         )),
-        SetLiteral::IntRange(i1, i2) => ret.push(format!("range,(value,{},value,{})", i1, i2)),
         SetLiteral::SetFloats(v) => {
             if v.is_empty() {
                 ret.push("empty_set".to_string());
             } else {
                 for f in v {
-                    ret.push(format!("set,(value,{})", float_literal(*f)));
+                    ret.push(format!("set,(value,{})", float_literal(*f, config)));
                 }
             }
         }
@@ -1331,50 +3440,214 @@ fn dec_set_literal(l: &SetLiteral) -> Vec<String> {
                 ret.push("empty_set".to_string());
             } else {
                 for f in v {
-                    ret.push(format!("set,(value,{})", f));
+                    ret.push(format!("set,(value,{})", int_literal(f, config)));
                 }
             }
         }
     }
     ret
 }
-fn write_output_var(mut buf: impl Write, id: &str, annos: &[Annotation]) -> Result<()> {
+fn write_output_var(
+    out: &mut impl Sink,
+    id: &str,
+    annos: &[Annotation],
+    symbols: &mut SymbolTable,
+    config: &Config,
+) -> Result<()> {
     for a in annos {
         if a.id == "output_var" {
-            writeln!(buf, "output_var({}).", identifier(id))?;
+            let buf = out.writer(Category::Outputs)?;
+            writeln!(buf, "output_var({}).", identifier(id, config))?;
+            write_show(buf, id, symbols, config)?;
             break;
         }
     }
     Ok(())
 }
-fn write_output_array(mut buf: impl Write, id: &str, annos: &[Annotation]) -> Result<()> {
+fn write_output_array(
+    out: &mut impl Sink,
+    id: &str,
+    annos: &[Annotation],
+    elements: &[&str],
+    symbols: &mut SymbolTable,
+    config: &Config,
+) -> Result<()> {
     for a in annos {
         if a.id == "output_array" {
-            match a.expressions.get(0) {
-                Some(AnnExpr::Expr(Expr::ArrayOfSet(v))) => {
-                    for (pos, e) in v.iter().enumerate() {
-                        match e {
-                            SetExpr::Set(SetLiteralExpr::IntInRange(
-                                IntExpr::Int(lb),
-                                IntExpr::Int(ub),
-                            )) => {
-                                writeln!(
-                                    buf,
-                                    "output_array({},{},({},{})).",
-                                    identifier(id),
-                                    pos,
-                                    int_literal(lb),
-                                    int_literal(ub)
-                                )?;
+            let index_sets = match a.expressions.first() {
+                Some(AnnExpr::Expr(e)) => output_index_sets(id, e, config)?,
+                _ => {
+                    return Err(FlatZincError::ExpectedIndexSets { id: id.to_string() }.into());
+                }
+            };
+            let buf = out.writer(Category::Outputs)?;
+            for (pos, index_set) in index_sets.iter().enumerate() {
+                writeln!(
+                    buf,
+                    "output_array({},{},{}).",
+                    identifier(id, config),
+                    pos,
+                    index_set
+                )?;
+            }
+            for element in elements {
+                write_show(&mut *buf, element, symbols, config)?;
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+/// Returns the index sets of an `output_array` annotation as `(LowerBound,UpperBound)`,
+/// or as `(var,Identifier)` if the index set is given by a parameter
+fn output_index_sets(id: &str, e: &Expr, config: &Config) -> Result<Vec<String>> {
+    let unsupported = |pos: usize, set: String| FlatZincError::UnsupportedIndexSet {
+        id: id.to_string(),
+        pos,
+        set,
+    };
+    let parameter = |par_id: &str| format!("(var,{})", identifier(par_id, config));
+    let mut ret = vec![];
+    match e {
+        Expr::ArrayOfSet(v) => {
+            for (pos, se) in v.iter().enumerate() {
+                match se {
+                    SetExpr::Set(SetLiteralExpr::IntInRange(
+                        IntExpr::Int(lb),
+                        IntExpr::Int(ub),
+                    )) => ret.push(format!(
+                        "({},{})",
+                        int_literal(lb, config),
+                        int_literal(ub, config)
+                    )),
+                    SetExpr::Set(SetLiteralExpr::SetInts(v)) => {
+                        let mut ints = vec![];
+                        for e in v {
+                            match e {
+                                IntExpr::Int(i) => ints.push(*i),
+                                _ => return Err(unsupported(pos, format!("{:?}", se)).into()),
                             }
-                            x => panic!("unexpected set expr: {:?}", x),
+                        }
+                        ints.sort_unstable();
+                        ints.dedup();
+                        match (ints.first(), ints.last()) {
+                            // the length of the range must not overflow for extreme bounds
+                            (Some(lb), Some(ub))
+                                if ub.checked_sub(*lb).and_then(|d| d.checked_add(1))
+                                    == Some(ints.len() as i128) =>
+                            {
+                                ret.push(format!(
+                                    "({},{})",
+                                    int_literal(lb, config),
+                                    int_literal(ub, config)
+                                ))
+                            }
+                            (None, None) => ret.push("(1,0)".to_string()),
+                            _ => return Err(unsupported(pos, format!("{:?}", se)).into()),
                         }
                     }
+                    SetExpr::VarParIdentifier(par_id) => ret.push(parameter(par_id)),
+                    x => return Err(unsupported(pos, format!("{:?}", x)).into()),
                 }
-                _ => panic!("expected an array of index sets!"),
             }
-            break;
         }
+        // a list of identifiers is parsed as array of bool
+        Expr::ArrayOfBool(v) => {
+            for (pos, e) in v.iter().enumerate() {
+                match e {
+                    BoolExpr::VarParIdentifier(par_id) => ret.push(parameter(par_id)),
+                    x => return Err(unsupported(pos, format!("{:?}", x)).into()),
+                }
+            }
+        }
+        _ => return Err(FlatZincError::ExpectedIndexSets { id: id.to_string() }.into()),
+    }
+    Ok(ret)
+}
+/// Writes a `#show` directive for the variable `id` using the template in `config`,
+/// unless one was already written; the value of an alias is taken from its representative
+fn write_show(
+    mut buf: impl Write,
+    id: &str,
+    symbols: &mut SymbolTable,
+    config: &Config,
+) -> Result<()> {
+    if let Some(template) = &config.show {
+        if !symbols.mark_shown(id) {
+            return Ok(());
+        }
+        let atom = show_atom(template, &identifier(id, config));
+        let body = show_atom(template, &identifier(symbols.representative(id), config));
+        writeln!(buf, "#show {} : {}.", atom, body)?;
     }
     Ok(())
 }
+/// Replaces every occurrence of the variable `X` in `template` by `id`
+fn show_atom(template: &str, id: &str) -> String {
+    let mut ret = String::new();
+    let mut in_string = false;
+    let mut prev = ' ';
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied().unwrap_or(' ');
+        if c == '"' && prev != '\\' {
+            in_string = !in_string;
+        }
+        let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '\'';
+        if c == 'X' && !in_string && !is_name(prev) && !is_name(next) {
+            ret.push_str(id);
+        } else {
+            ret.push(c);
+        }
+        prev = c;
+    }
+    ret
+}
+fn bool_array_identifiers(e: &Option<ArrayOfBoolExpr>) -> Vec<&str> {
+    match e {
+        Some(ArrayOfBoolExpr::Array(v)) => v
+            .iter()
+            .filter_map(|e| match e {
+                BoolExpr::VarParIdentifier(id) => Some(id.as_str()),
+                BoolExpr::Bool(_) => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+fn int_array_identifiers(e: &Option<ArrayOfIntExpr>) -> Vec<&str> {
+    match e {
+        Some(ArrayOfIntExpr::Array(v)) => v
+            .iter()
+            .filter_map(|e| match e {
+                IntExpr::VarParIdentifier(id) => Some(id.as_str()),
+                IntExpr::Int(_) => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+fn float_array_identifiers(e: &Option<ArrayOfFloatExpr>) -> Vec<&str> {
+    match e {
+        Some(ArrayOfFloatExpr::Array(v)) => v
+            .iter()
+            .filter_map(|e| match e {
+                FloatExpr::VarParIdentifier(id) => Some(id.as_str()),
+                FloatExpr::Float(_) => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+fn set_array_identifiers(e: &Option<ArrayOfSetExpr>) -> Vec<&str> {
+    match e {
+        Some(ArrayOfSetExpr::Array(v)) => v
+            .iter()
+            .filter_map(|e| match e {
+                SetExpr::VarParIdentifier(id) => Some(id.as_str()),
+                SetExpr::Set(_) => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
@@ -0,0 +1,613 @@
+use flatzinc::*;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Domain of a scalar variable during presolving
+#[derive(Debug, Clone, PartialEq)]
+enum Domain {
+    Bool(Option<bool>),
+    /// Bounds and, for set domains, the remaining values
+    Int {
+        lb: Option<i128>,
+        ub: Option<i128>,
+        set: Option<Vec<i128>>,
+    },
+}
+impl Domain {
+    fn fixed_int(&self) -> Option<i128> {
+        match self {
+            Domain::Int {
+                lb: Some(lb),
+                ub: Some(ub),
+                ..
+            } if lb == ub => Some(*lb),
+            _ => None,
+        }
+    }
+    /// Restricts a set domain to its bounds and the bounds to the set,
+    /// returns false if the domain becomes empty
+    fn normalize(&mut self) -> bool {
+        if let Domain::Int { lb, ub, set } = self {
+            if let Some(set) = set {
+                set.retain(|v| lb.is_none_or(|lb| lb <= *v) && ub.is_none_or(|ub| *v <= ub));
+                *lb = set.first().copied();
+                *ub = set.last().copied();
+                return !set.is_empty();
+            }
+            if let (Some(l), Some(u)) = (lb, ub) {
+                return l <= u;
+            }
+        }
+        true
+    }
+    fn describe(&self) -> String {
+        if let Some(v) = self.fixed_int() {
+            return v.to_string();
+        }
+        match self {
+            Domain::Bool(Some(b)) => b.to_string(),
+            Domain::Bool(None) => "bool".to_string(),
+            Domain::Int { set: Some(set), .. } => {
+                let values: Vec<String> = set.iter().map(|v| v.to_string()).collect();
+                format!("{{{}}}", values.join(","))
+            }
+            Domain::Int { lb, ub, .. } => format!(
+                "{}..{}",
+                lb.map_or("-infinity".to_string(), |v| v.to_string()),
+                ub.map_or("infinity".to_string(), |v| v.to_string())
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Variable {
+    /// Index of the declaration in the model
+    index: usize,
+    declared: Domain,
+    domain: Domain,
+}
+
+/// Argument of a constraint that presolving can reason about
+enum Arg<'a> {
+    Bool(bool),
+    Int(i128),
+    Var(&'a str),
+    Other,
+}
+
+/// Result of a lightweight presolve pass
+///
+/// Variables with singleton domains are fixed, `int_eq` and `bool_eq` with a constant fix
+/// the variable, `int_le` with a constant tightens its bounds, and constraints that are
+/// satisfied by the resulting domains are removed.
+/// Only scalar bool and int variables that are not assigned in their declaration are changed.
+#[derive(Debug, Default)]
+pub struct Presolve {
+    /// Updated variable declarations by the index of the statement
+    declarations: HashMap<usize, VarDeclItem>,
+    removed: HashSet<usize>,
+    log: Vec<String>,
+}
+impl Presolve {
+    pub fn new(stmts: &[FzStmt]) -> Self {
+        let mut constants = HashMap::new();
+        let mut variables = HashMap::new();
+        let mut constraints = vec![];
+        for (index, stmt) in stmts.iter().enumerate() {
+            match stmt {
+                FzStmt::Parameter(ParDeclItem::Bool { id, bool }) => {
+                    constants.insert(id.as_str(), Domain::Bool(Some(*bool)));
+                }
+                FzStmt::Parameter(ParDeclItem::Int { id, int }) => {
+                    constants.insert(id.as_str(), int_domain(Some(*int), Some(*int), None));
+                }
+                FzStmt::Variable(d) => {
+                    if let Some((id, domain)) = declared_domain(d) {
+                        variables.insert(
+                            id,
+                            Variable {
+                                index,
+                                declared: domain.clone(),
+                                domain,
+                            },
+                        );
+                    }
+                }
+                FzStmt::Constraint(c)
+                    if c.exprs.len() == 2
+                        && ["int_eq", "bool_eq", "int_le"].contains(&c.id.as_str()) =>
+                {
+                    constraints.push((index, c));
+                }
+                _ => {}
+            }
+        }
+        let mut presolve = Presolve::default();
+        for (id, var) in variables.iter_mut() {
+            if !var.domain.normalize() {
+                return Presolve::infeasible(format!("empty domain of {}", id));
+            }
+            var.declared = var.domain.clone();
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, c) in &constraints {
+                if presolve.removed.contains(index) {
+                    continue;
+                }
+                let a = arg(&c.exprs[0]);
+                let b = arg(&c.exprs[1]);
+                let (a, b) = (
+                    domain_of(&a, &constants, &variables),
+                    domain_of(&b, &constants, &variables),
+                );
+                let outcome = match c.id.as_str() {
+                    "int_le" => int_le(&a, &b),
+                    _ => eq(&a, &b),
+                };
+                match outcome {
+                    Outcome::Satisfied => {
+                        presolve.removed.insert(*index);
+                    }
+                    Outcome::Violated => {
+                        return Presolve::infeasible(format!(
+                            "constraint {} in line {} is violated",
+                            c.id,
+                            index + 1
+                        ))
+                    }
+                    Outcome::Restrict(restrictions) => {
+                        for (pos, restricted) in restrictions {
+                            let id = match arg(&c.exprs[pos]) {
+                                Arg::Var(id) => id,
+                                _ => continue,
+                            };
+                            let var = match variables.get_mut(id) {
+                                Some(var) => var,
+                                None => continue,
+                            };
+                            if var.domain != restricted {
+                                var.domain = restricted;
+                                if !var.domain.normalize() {
+                                    return Presolve::infeasible(format!("empty domain of {}", id));
+                                }
+                                changed = true;
+                            }
+                        }
+                    }
+                    Outcome::Unknown => {}
+                }
+            }
+        }
+        let mut updated: Vec<(&str, &Variable)> = variables
+            .iter()
+            .map(|(id, var)| (*id, var))
+            .filter(|(_, var)| var.domain != var.declared || is_fixed(&var.domain))
+            .collect();
+        updated.sort_by_key(|(_, var)| var.index);
+        for (id, var) in updated {
+            let item = match &stmts[var.index] {
+                FzStmt::Variable(d) => d,
+                _ => continue,
+            };
+            if let Some(item) = updated_declaration(item, &var.domain) {
+                presolve.log.push(if is_fixed(&var.domain) {
+                    format!("Fixed {} to {}", id, var.domain.describe())
+                } else {
+                    format!(
+                        "Tightened the domain of {} to {}",
+                        id,
+                        var.domain.describe()
+                    )
+                });
+                presolve.declarations.insert(var.index, item);
+            }
+        }
+        let mut removed: Vec<&usize> = presolve.removed.iter().collect();
+        removed.sort();
+        for index in removed {
+            if let FzStmt::Constraint(c) = &stmts[*index] {
+                presolve.log.push(format!(
+                    "Removed satisfied constraint {} in line {}",
+                    c.id,
+                    index + 1
+                ));
+            }
+        }
+        presolve
+    }
+    fn infeasible(reason: String) -> Self {
+        Presolve {
+            log: vec![format!(
+                "The model is infeasible, {}; nothing changed",
+                reason
+            )],
+            ..Default::default()
+        }
+    }
+    /// Returns true if the statement with the given index is removed
+    pub fn removes(&self, index: usize) -> bool {
+        self.removed.contains(&index)
+    }
+    /// Replaces the statement with the given index by its presolved version
+    pub fn apply(&self, index: usize, stmt: &mut FzStmt) {
+        if let Some(item) = self.declarations.get(&index) {
+            *stmt = FzStmt::Variable(item.clone());
+        }
+    }
+    /// Writes the lines of the presolved FlatZinc model
+    pub fn write_fzn(
+        &self,
+        mut out: impl Write,
+        stmts: &[FzStmt],
+        lines: &[String],
+    ) -> io::Result<()> {
+        for (index, (_, line)) in stmts.iter().zip(lines).enumerate() {
+            if self.removes(index) {
+                continue;
+            }
+            match self.declarations.get(&index) {
+                Some(item) => writeln!(out, "{}", edit_var_decl(line, item))?,
+                None => writeln!(out, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+    pub fn write_log(&self, mut out: impl Write) -> io::Result<()> {
+        for line in &self.log {
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+enum Outcome {
+    Satisfied,
+    Violated,
+    /// New domains for the arguments at the given positions
+    Restrict(Vec<(usize, Domain)>),
+    Unknown,
+}
+fn eq(a: &Option<Domain>, b: &Option<Domain>) -> Outcome {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Outcome::Unknown,
+    };
+    match (is_fixed(a), is_fixed(b)) {
+        (true, true) if a == b => Outcome::Satisfied,
+        (true, true) => match (a.fixed_int(), b.fixed_int()) {
+            (Some(x), Some(y)) if x == y => Outcome::Satisfied,
+            _ => Outcome::Violated,
+        },
+        (true, false) => Outcome::Restrict(vec![(1, fix(b, a))]),
+        (false, true) => Outcome::Restrict(vec![(0, fix(a, b))]),
+        (false, false) => Outcome::Unknown,
+    }
+}
+/// Restricts `domain` to the value of the fixed domain `value`
+fn fix(domain: &Domain, value: &Domain) -> Domain {
+    match (domain, value) {
+        (Domain::Int { lb, ub, set }, Domain::Int { .. }) => {
+            let v = value.fixed_int();
+            let contained = v.is_some_and(|v| {
+                lb.is_none_or(|lb| lb <= v)
+                    && ub.is_none_or(|ub| v <= ub)
+                    && set.as_ref().is_none_or(|set| set.contains(&v))
+            });
+            if contained {
+                int_domain(v, v, set.as_ref().map(|_| v.into_iter().collect()))
+            } else {
+                int_domain(Some(1), Some(0), None)
+            }
+        }
+        _ => value.clone(),
+    }
+}
+fn int_le(a: &Option<Domain>, b: &Option<Domain>) -> Outcome {
+    let (a_lb, a_ub, b_lb, b_ub) = match (a, b) {
+        (Some(Domain::Int { lb: al, ub: au, .. }), Some(Domain::Int { lb: bl, ub: bu, .. })) => {
+            (*al, *au, *bl, *bu)
+        }
+        _ => return Outcome::Unknown,
+    };
+    let bounded = |d: &Option<Domain>| {
+        matches!(
+            d,
+            Some(Domain::Int {
+                lb: Some(_),
+                ub: Some(_),
+                ..
+            })
+        )
+    };
+    if let (Some(au), Some(bl)) = (a_ub, b_lb) {
+        if au <= bl && bounded(a) && bounded(b) {
+            return Outcome::Satisfied;
+        }
+    }
+    if let (Some(al), Some(bu)) = (a_lb, b_ub) {
+        if al > bu {
+            return Outcome::Violated;
+        }
+    }
+    let mut restrictions = vec![];
+    if let (Some(Domain::Int { lb, ub, set }), Some(c)) =
+        (a, b.as_ref().and_then(Domain::fixed_int))
+    {
+        if ub.is_none_or(|ub| ub > c) {
+            restrictions.push((0, int_domain(*lb, Some(c), set.clone())));
+        }
+    }
+    if let (Some(c), Some(Domain::Int { lb, ub, set })) =
+        (a.as_ref().and_then(Domain::fixed_int), b)
+    {
+        if lb.is_none_or(|lb| lb < c) {
+            restrictions.push((1, int_domain(Some(c), *ub, set.clone())));
+        }
+    }
+    if restrictions.is_empty() {
+        Outcome::Unknown
+    } else {
+        Outcome::Restrict(restrictions)
+    }
+}
+fn int_domain(lb: Option<i128>, ub: Option<i128>, set: Option<Vec<i128>>) -> Domain {
+    Domain::Int { lb, ub, set }
+}
+fn is_fixed(domain: &Domain) -> bool {
+    match domain {
+        Domain::Bool(b) => b.is_some(),
+        Domain::Int { .. } => domain.fixed_int().is_some(),
+    }
+}
+fn arg(e: &Expr) -> Arg<'_> {
+    match e {
+        Expr::Bool(b) => Arg::Bool(*b),
+        Expr::Int(i) => Arg::Int(*i),
+        Expr::VarParIdentifier(id) => Arg::Var(id),
+        // a list of identifiers is parsed as array of bools
+        Expr::ArrayOfBool(v) if v.len() == 1 => match &v[0] {
+            BoolExpr::VarParIdentifier(id) => Arg::Var(id),
+            BoolExpr::Bool(b) => Arg::Bool(*b),
+        },
+        _ => Arg::Other,
+    }
+}
+fn domain_of(
+    arg: &Arg,
+    constants: &HashMap<&str, Domain>,
+    variables: &HashMap<&str, Variable>,
+) -> Option<Domain> {
+    match arg {
+        Arg::Bool(b) => Some(Domain::Bool(Some(*b))),
+        Arg::Int(i) => Some(int_domain(Some(*i), Some(*i), None)),
+        Arg::Var(id) => constants
+            .get(id)
+            .or_else(|| variables.get(id).map(|var| &var.domain))
+            .cloned(),
+        Arg::Other => None,
+    }
+}
+/// Returns the domain of a scalar bool or int variable that is not assigned a value
+fn declared_domain(item: &VarDeclItem) -> Option<(&str, Domain)> {
+    match item {
+        VarDeclItem::Bool { id, expr: None, .. } => Some((id, Domain::Bool(None))),
+        VarDeclItem::Int { id, expr: None, .. } => Some((id, int_domain(None, None, None))),
+        VarDeclItem::IntInRange {
+            id,
+            lb,
+            ub,
+            expr: None,
+            ..
+        } => Some((id, int_domain(Some(*lb), Some(*ub), None))),
+        VarDeclItem::IntInSet {
+            id,
+            set,
+            expr: None,
+            ..
+        } => {
+            let mut set = set.clone();
+            set.sort_unstable();
+            set.dedup();
+            Some((id, int_domain(None, None, Some(set))))
+        }
+        _ => None,
+    }
+}
+/// Returns the declaration with the presolved domain,
+/// or None if the domain can not be stated in the declaration
+fn updated_declaration(item: &VarDeclItem, domain: &Domain) -> Option<VarDeclItem> {
+    match (item, domain) {
+        (VarDeclItem::Bool { id, annos, .. }, Domain::Bool(Some(b))) => Some(VarDeclItem::Bool {
+            id: id.clone(),
+            expr: Some(BoolExpr::Bool(*b)),
+            annos: annos.clone(),
+        }),
+        (
+            VarDeclItem::Int { id, annos, .. }
+            | VarDeclItem::IntInRange { id, annos, .. }
+            | VarDeclItem::IntInSet { id, annos, .. },
+            Domain::Int {
+                lb: Some(lb),
+                ub: Some(ub),
+                set,
+            },
+        ) => {
+            let expr = if lb == ub {
+                Some(IntExpr::Int(*lb))
+            } else {
+                None
+            };
+            Some(match set {
+                Some(set) if lb != ub => VarDeclItem::IntInSet {
+                    id: id.clone(),
+                    set: set.clone(),
+                    expr,
+                    annos: annos.clone(),
+                },
+                _ => VarDeclItem::IntInRange {
+                    id: id.clone(),
+                    lb: *lb,
+                    ub: *ub,
+                    expr,
+                    annos: annos.clone(),
+                },
+            })
+        }
+        _ => None,
+    }
+}
+/// Replaces the domain in the line of a variable declaration and adds its value, if fixed
+fn edit_var_decl(line: &str, item: &VarDeclItem) -> String {
+    let (domain, value) = match item {
+        VarDeclItem::Bool { expr, .. } => (
+            "bool".to_string(),
+            match expr {
+                Some(BoolExpr::Bool(b)) => Some(b.to_string()),
+                _ => None,
+            },
+        ),
+        VarDeclItem::IntInRange { lb, ub, expr, .. } => (
+            format!("{}..{}", lb, ub),
+            match expr {
+                Some(IntExpr::Int(i)) => Some(i.to_string()),
+                _ => None,
+            },
+        ),
+        VarDeclItem::IntInSet { set, .. } => {
+            let values: Vec<String> = set.iter().map(|v| v.to_string()).collect();
+            (format!("{{{}}}", values.join(",")), None)
+        }
+        _ => return line.to_string(),
+    };
+    let start = match line.find("var ") {
+        Some(start) => start + 4,
+        None => return line.to_string(),
+    };
+    let end = match line[start..].find(':') {
+        Some(end) => start + end,
+        None => return line.to_string(),
+    };
+    let mut line = format!("{}{}{}", &line[..start], domain, &line[end..]);
+    if let (Some(value), Some(semicolon)) = (value, line.rfind(';')) {
+        line.insert_str(semicolon, &format!(" = {}", value));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_fz_stmt;
+
+    /// Presolves the model, returns the presolved model and the log
+    fn presolve(lines: &[&str]) -> (String, String) {
+        let stmts: Vec<_> = lines.iter().map(|s| parse_fz_stmt(s).unwrap().0).collect();
+        let lines: Vec<_> = lines.iter().map(|s| s.to_string()).collect();
+        let presolve = Presolve::new(&stmts);
+        let (mut fzn, mut log) = (Vec::new(), Vec::new());
+        presolve.write_fzn(&mut fzn, &stmts, &lines).unwrap();
+        presolve.write_log(&mut log).unwrap();
+        (
+            String::from_utf8(fzn).unwrap(),
+            String::from_utf8(log).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut d = int_domain(Some(2), Some(5), Some(vec![1, 3, 4, 7]));
+        assert!(d.normalize());
+        assert_eq!(d, int_domain(Some(3), Some(4), Some(vec![3, 4])));
+        let mut d = int_domain(Some(5), None, Some(vec![1, 3]));
+        assert!(!d.normalize());
+        let mut d = int_domain(Some(3), Some(2), None);
+        assert!(!d.normalize());
+        let mut d = int_domain(None, Some(2), None);
+        assert!(d.normalize());
+    }
+    #[test]
+    fn test_int_le_bounds() {
+        let x = Some(int_domain(Some(1), Some(10), None));
+        let c = |v| Some(int_domain(Some(v), Some(v), None));
+        match int_le(&x, &c(4)) {
+            Outcome::Restrict(r) => assert_eq!(r, vec![(0, int_domain(Some(1), Some(4), None))]),
+            _ => panic!("x <= 4 does not tighten x"),
+        }
+        match int_le(&c(4), &x) {
+            Outcome::Restrict(r) => assert_eq!(r, vec![(1, int_domain(Some(4), Some(10), None))]),
+            _ => panic!("4 <= x does not tighten x"),
+        }
+        assert!(matches!(int_le(&x, &c(10)), Outcome::Satisfied));
+        assert!(matches!(int_le(&c(11), &x), Outcome::Violated));
+        let y = Some(int_domain(Some(1), Some(10), None));
+        assert!(matches!(int_le(&x, &y), Outcome::Unknown));
+        // unbounded variables are never considered satisfied
+        let z = Some(int_domain(None, Some(1), None));
+        assert!(matches!(int_le(&z, &c(1)), Outcome::Unknown));
+    }
+    #[test]
+    fn test_fix() {
+        let x = int_domain(Some(1), Some(3), Some(vec![1, 3]));
+        let c = |v| int_domain(Some(v), Some(v), None);
+        assert_eq!(fix(&x, &c(3)), int_domain(Some(3), Some(3), Some(vec![3])));
+        assert!(!fix(&x, &c(2)).normalize());
+        assert_eq!(
+            fix(&Domain::Bool(None), &Domain::Bool(Some(true))),
+            Domain::Bool(Some(true))
+        );
+    }
+    #[test]
+    fn test_presolve_model() {
+        let (fzn, log) = presolve(&[
+            "int: n = 2;",
+            "var 1..5: x;",
+            "var {1,3,5}: y;",
+            "var bool: b;",
+            "var int: z = x;",
+            "constraint int_le(x,n);",
+            "constraint int_le(1,y);",
+            "constraint bool_eq(b,true);",
+            "constraint int_eq(y,3);",
+            "constraint int_le(x,z);",
+            "solve satisfy;",
+        ]);
+        assert_eq!(
+            fzn,
+            "int: n = 2;\n\
+             var 1..2: x;\n\
+             var 3..3: y = 3;\n\
+             var bool: b = true;\n\
+             var int: z = x;\n\
+             constraint int_le(x,z);\n\
+             solve satisfy;\n"
+        );
+        assert_eq!(
+            log,
+            "Tightened the domain of x to 1..2\n\
+             Fixed y to 3\n\
+             Fixed b to true\n\
+             Removed satisfied constraint int_le in line 6\n\
+             Removed satisfied constraint int_le in line 7\n\
+             Removed satisfied constraint bool_eq in line 8\n\
+             Removed satisfied constraint int_eq in line 9\n"
+        );
+    }
+    #[test]
+    fn test_presolve_infeasible() {
+        let model = [
+            "var 1..5: x;",
+            "constraint int_le(x,3);",
+            "constraint int_eq(x,4);",
+            "solve satisfy;",
+        ];
+        let (fzn, log) = presolve(&model);
+        assert_eq!(fzn, model.join("\n") + "\n");
+        assert_eq!(
+            log,
+            "The model is infeasible, empty domain of x; nothing changed\n"
+        );
+    }
+}
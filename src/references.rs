@@ -0,0 +1,282 @@
+use crate::{Config, FlatZincError, SymbolTable};
+use anyhow::Result;
+use flatzinc::*;
+use log::warn;
+
+/// Checks that a declaration does not redeclare an identifier
+/// and that all identifiers referenced by a statement are declared
+///
+/// This covers constraint arguments, variable assignments, output annotations and the objective.
+/// Issues are reported as errors, or as warnings in lenient mode.
+pub(crate) fn check_stmt(
+    stmt: &FzStmt,
+    par_annos: &[Annotation],
+    symbols: &SymbolTable,
+    config: &Config,
+) -> Result<()> {
+    match stmt {
+        FzStmt::Comment(_) | FzStmt::Predicate(_) => {}
+        FzStmt::Parameter(p) => {
+            let id = par_decl_id(p);
+            let checker = Checker::new(symbols, config, format!("parameter {}", id));
+            checker.declaration(id)?;
+            checker.annotations(par_annos)?;
+        }
+        FzStmt::Variable(d) => {
+            let (id, annos) = var_decl_id_annos(d);
+            let checker = Checker::new(symbols, config, format!("variable {}", id));
+            checker.declaration(id)?;
+            checker.var_decl_item(d)?;
+            checker.annotations(annos)?;
+        }
+        FzStmt::Constraint(c) => {
+            for (pos, e) in c.exprs.iter().enumerate() {
+                Checker::new(
+                    symbols,
+                    config,
+                    format!("constraint {} argument {}", c.id, pos),
+                )
+                .expr(e)?;
+            }
+        }
+        FzStmt::SolveItem(i) => {
+            let checker = Checker::new(symbols, config, "solve item".to_string());
+            match &i.goal {
+                Goal::Satisfy => {}
+                Goal::OptimizeBool(_, e) => checker.bool_expr(e)?,
+                Goal::OptimizeInt(_, e) => checker.int_expr(e)?,
+                Goal::OptimizeFloat(_, e) => checker.float_expr(e)?,
+                Goal::OptimizeSet(_, e) => checker.set_expr(e)?,
+            }
+        }
+    }
+    Ok(())
+}
+pub(crate) fn par_decl_id(item: &ParDeclItem) -> &str {
+    match item {
+        ParDeclItem::Bool { id, .. }
+        | ParDeclItem::Int { id, .. }
+        | ParDeclItem::Float { id, .. }
+        | ParDeclItem::SetOfInt { id, .. }
+        | ParDeclItem::ArrayOfBool { id, .. }
+        | ParDeclItem::ArrayOfInt { id, .. }
+        | ParDeclItem::ArrayOfFloat { id, .. }
+        | ParDeclItem::ArrayOfSet { id, .. } => id,
+    }
+}
+pub(crate) fn var_decl_id_annos(item: &VarDeclItem) -> (&str, &[Annotation]) {
+    match item {
+        VarDeclItem::Bool { id, annos, .. }
+        | VarDeclItem::Int { id, annos, .. }
+        | VarDeclItem::IntInRange { id, annos, .. }
+        | VarDeclItem::IntInSet { id, annos, .. }
+        | VarDeclItem::Float { id, annos, .. }
+        | VarDeclItem::BoundedFloat { id, annos, .. }
+        | VarDeclItem::SetOfInt { id, annos, .. }
+        | VarDeclItem::SubSetOfIntRange { id, annos, .. }
+        | VarDeclItem::SubSetOfIntSet { id, annos, .. }
+        | VarDeclItem::ArrayOfBool { id, annos, .. }
+        | VarDeclItem::ArrayOfInt { id, annos, .. }
+        | VarDeclItem::ArrayOfIntInRange { id, annos, .. }
+        | VarDeclItem::ArrayOfIntInSet { id, annos, .. }
+        | VarDeclItem::ArrayOfFloat { id, annos, .. }
+        | VarDeclItem::ArrayOfBoundedFloat { id, annos, .. }
+        | VarDeclItem::ArrayOfSet { id, annos, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntRange { id, annos, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntSet { id, annos, .. } => (id, annos),
+    }
+}
+struct Checker<'a> {
+    symbols: &'a SymbolTable,
+    config: &'a Config,
+    location: String,
+}
+impl<'a> Checker<'a> {
+    fn new(symbols: &'a SymbolTable, config: &'a Config, location: String) -> Self {
+        Checker {
+            symbols,
+            config,
+            location,
+        }
+    }
+    fn report(&self, err: FlatZincError) -> Result<()> {
+        if self.config.lenient {
+            warn!("{}", err);
+            Ok(())
+        } else {
+            Err(err.into())
+        }
+    }
+    fn declaration(&self, id: &str) -> Result<()> {
+        if self.symbols.get(id).is_some() {
+            return self.report(FlatZincError::DuplicateDeclaration { id: id.to_string() });
+        }
+        Ok(())
+    }
+    fn identifier(&self, id: &str) -> Result<()> {
+        if self.symbols.get(id).is_none() {
+            return self.report(FlatZincError::UndefinedIdentifier {
+                id: id.to_string(),
+                location: self.location.clone(),
+            });
+        }
+        Ok(())
+    }
+    fn var_decl_item(&self, item: &VarDeclItem) -> Result<()> {
+        match item {
+            VarDeclItem::Bool { expr, .. } => {
+                if let Some(e) = expr {
+                    self.bool_expr(e)?;
+                }
+            }
+            VarDeclItem::Int { expr, .. }
+            | VarDeclItem::IntInRange { expr, .. }
+            | VarDeclItem::IntInSet { expr, .. } => {
+                if let Some(e) = expr {
+                    self.int_expr(e)?;
+                }
+            }
+            VarDeclItem::Float { expr, .. } | VarDeclItem::BoundedFloat { expr, .. } => {
+                if let Some(e) = expr {
+                    self.float_expr(e)?;
+                }
+            }
+            VarDeclItem::SetOfInt { expr, .. }
+            | VarDeclItem::SubSetOfIntRange { expr, .. }
+            | VarDeclItem::SubSetOfIntSet { expr, .. } => {
+                if let Some(e) = expr {
+                    self.set_expr(e)?;
+                }
+            }
+            VarDeclItem::ArrayOfBool { array_expr, .. } => match array_expr {
+                Some(ArrayOfBoolExpr::Array(v)) => {
+                    for e in v {
+                        self.bool_expr(e)?;
+                    }
+                }
+                Some(ArrayOfBoolExpr::VarParIdentifier(id)) => self.identifier(id)?,
+                None => {}
+            },
+            VarDeclItem::ArrayOfInt { array_expr, .. }
+            | VarDeclItem::ArrayOfIntInRange { array_expr, .. }
+            | VarDeclItem::ArrayOfIntInSet { array_expr, .. } => match array_expr {
+                Some(ArrayOfIntExpr::Array(v)) => {
+                    for e in v {
+                        self.int_expr(e)?;
+                    }
+                }
+                Some(ArrayOfIntExpr::VarParIdentifier(id)) => self.identifier(id)?,
+                None => {}
+            },
+            VarDeclItem::ArrayOfFloat { array_expr, .. }
+            | VarDeclItem::ArrayOfBoundedFloat { array_expr, .. } => match array_expr {
+                Some(ArrayOfFloatExpr::Array(v)) => {
+                    for e in v {
+                        self.float_expr(e)?;
+                    }
+                }
+                Some(ArrayOfFloatExpr::VarParIdentifier(id)) => self.identifier(id)?,
+                None => {}
+            },
+            VarDeclItem::ArrayOfSet { array_expr, .. }
+            | VarDeclItem::ArrayOfSubSetOfIntRange { array_expr, .. }
+            | VarDeclItem::ArrayOfSubSetOfIntSet { array_expr, .. } => match array_expr {
+                Some(ArrayOfSetExpr::Array(v)) => {
+                    for e in v {
+                        self.set_expr(e)?;
+                    }
+                }
+                Some(ArrayOfSetExpr::VarParIdentifier(id)) => self.identifier(id)?,
+                None => {}
+            },
+        }
+        Ok(())
+    }
+    /// Checks the index sets of `output_array` annotations given by parameters
+    fn annotations(&self, annos: &[Annotation]) -> Result<()> {
+        for a in annos {
+            if a.id == "output_array" {
+                if let Some(AnnExpr::Expr(e)) = a.expressions.first() {
+                    self.expr(e)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn expr(&self, e: &Expr) -> Result<()> {
+        match e {
+            Expr::VarParIdentifier(id) => self.identifier(id)?,
+            Expr::Bool(_) | Expr::Int(_) | Expr::Float(_) => {}
+            Expr::Set(sl) => self.set_literal_expr(sl)?,
+            Expr::ArrayOfBool(v) => {
+                for e in v {
+                    self.bool_expr(e)?;
+                }
+            }
+            Expr::ArrayOfInt(v) => {
+                for e in v {
+                    self.int_expr(e)?;
+                }
+            }
+            Expr::ArrayOfFloat(v) => {
+                for e in v {
+                    self.float_expr(e)?;
+                }
+            }
+            Expr::ArrayOfSet(v) => {
+                for e in v {
+                    self.set_expr(e)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn bool_expr(&self, e: &BoolExpr) -> Result<()> {
+        match e {
+            BoolExpr::VarParIdentifier(id) => self.identifier(id),
+            BoolExpr::Bool(_) => Ok(()),
+        }
+    }
+    fn int_expr(&self, e: &IntExpr) -> Result<()> {
+        match e {
+            IntExpr::VarParIdentifier(id) => self.identifier(id),
+            IntExpr::Int(_) => Ok(()),
+        }
+    }
+    fn float_expr(&self, e: &FloatExpr) -> Result<()> {
+        match e {
+            FloatExpr::VarParIdentifier(id) => self.identifier(id),
+            FloatExpr::Float(_) => Ok(()),
+        }
+    }
+    fn set_expr(&self, e: &SetExpr) -> Result<()> {
+        match e {
+            SetExpr::VarParIdentifier(id) => self.identifier(id),
+            SetExpr::Set(sl) => self.set_literal_expr(sl),
+        }
+    }
+    fn set_literal_expr(&self, sl: &SetLiteralExpr) -> Result<()> {
+        match sl {
+            SetLiteralExpr::IntInRange(lb, ub) => {
+                self.int_expr(lb)?;
+                self.int_expr(ub)
+            }
+            SetLiteralExpr::BoundedFloat(lb, ub) => {
+                self.float_expr(lb)?;
+                self.float_expr(ub)
+            }
+            SetLiteralExpr::SetInts(v) => {
+                for e in v {
+                    self.int_expr(e)?;
+                }
+                Ok(())
+            }
+            SetLiteralExpr::SetFloats(v) => {
+                for e in v {
+                    self.float_expr(e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
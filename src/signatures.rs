@@ -0,0 +1,533 @@
+use crate::symbols::{BaseType, SymbolKind, SymbolTable};
+use crate::{Config, FlatZincError};
+use anyhow::Result;
+use flatzinc::*;
+use log::warn;
+use std::fmt;
+
+/// Signatures of the standard FlatZinc builtins
+///
+/// A parameter is written as `[var ]<type>`, where `<type>` is `bool`, `int`, `float` or `set`,
+/// and is enclosed in brackets if it is an array.
+/// A builtin with several arities, like `bool_xor`, is listed once for each of them.
+const BUILTINS: &[(&str, &str)] = &[
+    ("array_int_element", "var int, [int], var int"),
+    ("array_var_int_element", "var int, [var int], var int"),
+    ("array_int_maximum", "var int, [var int]"),
+    ("array_int_minimum", "var int, [var int]"),
+    ("int_abs", "var int, var int"),
+    ("int_div", "var int, var int, var int"),
+    ("int_eq", "var int, var int"),
+    ("int_eq_reif", "var int, var int, var bool"),
+    ("int_eq_imp", "var int, var int, var bool"),
+    ("int_le", "var int, var int"),
+    ("int_le_reif", "var int, var int, var bool"),
+    ("int_le_imp", "var int, var int, var bool"),
+    ("int_lin_eq", "[int], [var int], int"),
+    ("int_lin_eq_reif", "[int], [var int], int, var bool"),
+    ("int_lin_eq_imp", "[int], [var int], int, var bool"),
+    ("int_lin_le", "[int], [var int], int"),
+    ("int_lin_le_reif", "[int], [var int], int, var bool"),
+    ("int_lin_le_imp", "[int], [var int], int, var bool"),
+    ("int_lin_ne", "[int], [var int], int"),
+    ("int_lin_ne_reif", "[int], [var int], int, var bool"),
+    ("int_lin_ne_imp", "[int], [var int], int, var bool"),
+    ("int_lt", "var int, var int"),
+    ("int_lt_reif", "var int, var int, var bool"),
+    ("int_lt_imp", "var int, var int, var bool"),
+    ("int_max", "var int, var int, var int"),
+    ("int_min", "var int, var int, var int"),
+    ("int_mod", "var int, var int, var int"),
+    ("int_ne", "var int, var int"),
+    ("int_ne_reif", "var int, var int, var bool"),
+    ("int_ne_imp", "var int, var int, var bool"),
+    ("int_plus", "var int, var int, var int"),
+    ("int_pow", "var int, var int, var int"),
+    ("int_times", "var int, var int, var int"),
+    ("array_bool_and", "[var bool], var bool"),
+    ("array_bool_and_imp", "[var bool], var bool"),
+    ("array_bool_element", "var int, [bool], var bool"),
+    ("array_var_bool_element", "var int, [var bool], var bool"),
+    ("array_bool_or", "[var bool], var bool"),
+    ("array_bool_or_imp", "[var bool], var bool"),
+    ("array_bool_xor", "[var bool]"),
+    ("bool2int", "var bool, var int"),
+    ("bool_and", "var bool, var bool, var bool"),
+    ("bool_and_imp", "var bool, var bool, var bool"),
+    ("bool_clause", "[var bool], [var bool]"),
+    ("bool_clause_imp", "[var bool], [var bool], var bool"),
+    ("bool_clause_reif", "[var bool], [var bool], var bool"),
+    ("bool_eq", "var bool, var bool"),
+    ("bool_eq_reif", "var bool, var bool, var bool"),
+    ("bool_eq_imp", "var bool, var bool, var bool"),
+    ("bool_le", "var bool, var bool"),
+    ("bool_le_reif", "var bool, var bool, var bool"),
+    ("bool_le_imp", "var bool, var bool, var bool"),
+    ("bool_lin_eq", "[int], [var bool], var int"),
+    ("bool_lin_le", "[int], [var bool], int"),
+    ("bool_lt", "var bool, var bool"),
+    ("bool_lt_reif", "var bool, var bool, var bool"),
+    ("bool_lt_imp", "var bool, var bool, var bool"),
+    ("bool_not", "var bool, var bool"),
+    ("bool_or", "var bool, var bool, var bool"),
+    ("bool_or_imp", "var bool, var bool, var bool"),
+    ("bool_xor", "var bool, var bool, var bool"),
+    ("bool_xor", "var bool, var bool"),
+    ("bool_xor_imp", "var bool, var bool, var bool"),
+    ("array_set_element", "var int, [set], var set"),
+    ("array_var_set_element", "var int, [var set], var set"),
+    ("set_card", "var set, var int"),
+    ("set_diff", "var set, var set, var set"),
+    ("set_eq", "var set, var set"),
+    ("set_eq_reif", "var set, var set, var bool"),
+    ("set_eq_imp", "var set, var set, var bool"),
+    ("set_in", "var int, var set"),
+    ("set_in_reif", "var int, var set, var bool"),
+    ("set_in_imp", "var int, var set, var bool"),
+    ("set_intersect", "var set, var set, var set"),
+    ("set_le", "var set, var set"),
+    ("set_le_reif", "var set, var set, var bool"),
+    ("set_le_imp", "var set, var set, var bool"),
+    ("set_lt", "var set, var set"),
+    ("set_lt_reif", "var set, var set, var bool"),
+    ("set_lt_imp", "var set, var set, var bool"),
+    ("set_ne", "var set, var set"),
+    ("set_ne_reif", "var set, var set, var bool"),
+    ("set_ne_imp", "var set, var set, var bool"),
+    ("set_subset", "var set, var set"),
+    ("set_subset_reif", "var set, var set, var bool"),
+    ("set_subset_imp", "var set, var set, var bool"),
+    ("set_superset", "var set, var set"),
+    ("set_superset_reif", "var set, var set, var bool"),
+    ("set_superset_imp", "var set, var set, var bool"),
+    ("set_symdiff", "var set, var set, var set"),
+    ("set_union", "var set, var set, var set"),
+    ("array_float_element", "var int, [float], var float"),
+    ("array_var_float_element", "var int, [var float], var float"),
+    ("array_float_maximum", "var float, [var float]"),
+    ("array_float_minimum", "var float, [var float]"),
+    ("float_abs", "var float, var float"),
+    ("float_acos", "var float, var float"),
+    ("float_acosh", "var float, var float"),
+    ("float_asin", "var float, var float"),
+    ("float_asinh", "var float, var float"),
+    ("float_atan", "var float, var float"),
+    ("float_atanh", "var float, var float"),
+    ("float_cos", "var float, var float"),
+    ("float_cosh", "var float, var float"),
+    ("float_div", "var float, var float, var float"),
+    ("float_dom", "var float, [float]"),
+    ("float_eq", "var float, var float"),
+    ("float_eq_reif", "var float, var float, var bool"),
+    ("float_eq_imp", "var float, var float, var bool"),
+    ("float_exp", "var float, var float"),
+    ("float_in", "var float, float, float"),
+    ("float_in_reif", "var float, float, float, var bool"),
+    ("float_le", "var float, var float"),
+    ("float_le_reif", "var float, var float, var bool"),
+    ("float_le_imp", "var float, var float, var bool"),
+    ("float_lin_eq", "[float], [var float], float"),
+    ("float_lin_eq_reif", "[float], [var float], float, var bool"),
+    ("float_lin_eq_imp", "[float], [var float], float, var bool"),
+    ("float_lin_le", "[float], [var float], float"),
+    ("float_lin_le_reif", "[float], [var float], float, var bool"),
+    ("float_lin_le_imp", "[float], [var float], float, var bool"),
+    ("float_lin_lt", "[float], [var float], float"),
+    ("float_lin_lt_reif", "[float], [var float], float, var bool"),
+    ("float_lin_lt_imp", "[float], [var float], float, var bool"),
+    ("float_lin_ne", "[float], [var float], float"),
+    ("float_lin_ne_reif", "[float], [var float], float, var bool"),
+    ("float_lin_ne_imp", "[float], [var float], float, var bool"),
+    ("float_ln", "var float, var float"),
+    ("float_log10", "var float, var float"),
+    ("float_log2", "var float, var float"),
+    ("float_lt", "var float, var float"),
+    ("float_lt_reif", "var float, var float, var bool"),
+    ("float_lt_imp", "var float, var float, var bool"),
+    ("float_max", "var float, var float, var float"),
+    ("float_min", "var float, var float, var float"),
+    ("float_ne", "var float, var float"),
+    ("float_ne_reif", "var float, var float, var bool"),
+    ("float_ne_imp", "var float, var float, var bool"),
+    ("float_plus", "var float, var float, var float"),
+    ("float_pow", "var float, var float, var float"),
+    ("float_sin", "var float, var float"),
+    ("float_sinh", "var float, var float"),
+    ("float_sqrt", "var float, var float"),
+    ("float_tan", "var float, var float"),
+    ("float_tanh", "var float, var float"),
+    ("float_times", "var float, var float, var float"),
+    ("int2float", "var int, var float"),
+];
+
+/// Type of a predicate parameter or a constraint argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ArgType {
+    /// Element type, `None` for an empty array literal
+    base: Option<BaseType>,
+    array: bool,
+    var: bool,
+}
+impl ArgType {
+    /// Returns whether an argument of type `actual` can be passed for a parameter of this type
+    fn accepts(&self, actual: &ArgType) -> bool {
+        self.array == actual.array
+            && (actual.base.is_none() || actual.base == self.base)
+            && (self.var || !actual.var)
+    }
+}
+impl fmt::Display for ArgType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.array {
+            write!(f, "array [int] of ")?;
+        }
+        if self.var {
+            write!(f, "var ")?;
+        }
+        match self.base {
+            Some(BaseType::Bool) => write!(f, "bool"),
+            Some(BaseType::Int) => write!(f, "int"),
+            Some(BaseType::Float) => write!(f, "float"),
+            Some(BaseType::SetOfInt) => write!(f, "set of int"),
+            None => write!(f, "_"),
+        }
+    }
+}
+
+/// Checks the arity and the argument types of a constraint against the predicates declared
+/// in the model and the FlatZinc builtins
+///
+/// Arguments whose type can not be determined are not checked.
+/// Issues are reported as errors, or as warnings in lenient mode.
+pub(crate) fn check_constraint(
+    c: &ConstraintItem,
+    i: usize,
+    symbols: &SymbolTable,
+    config: &Config,
+) -> Result<()> {
+    let report = |err: FlatZincError| -> Result<()> {
+        if config.lenient {
+            warn!("{}", err);
+            Ok(())
+        } else {
+            Err(err.into())
+        }
+    };
+    let constraint = format!("c{}", i);
+    let signature = match symbols.predicate(&c.id) {
+        Some(p) => p.parameters.iter().map(|(t, _)| pred_par_type(t)).collect(),
+        None => {
+            let mut signatures: Vec<Vec<ArgType>> = BUILTINS
+                .iter()
+                .filter(|(id, _)| *id == c.id)
+                .map(|(_, signature)| parse_signature(signature))
+                .collect();
+            if signatures.is_empty() {
+                return report(FlatZincError::UnknownConstraint {
+                    constraint,
+                    name: c.id.clone(),
+                });
+            }
+            // prefer the signature with the arity of the constraint
+            let pos = signatures
+                .iter()
+                .position(|s| s.len() == c.exprs.len())
+                .unwrap_or(0);
+            signatures.swap_remove(pos)
+        }
+    };
+    if signature.len() != c.exprs.len() {
+        return report(FlatZincError::ArityMismatch {
+            constraint,
+            name: c.id.clone(),
+            expected: signature.len(),
+            found: c.exprs.len(),
+        });
+    }
+    for (pos, (expected, e)) in signature.iter().zip(&c.exprs).enumerate() {
+        if let Some(found) = argument_type(e, symbols) {
+            if !expected.accepts(&found) {
+                report(FlatZincError::ArgumentTypeMismatch {
+                    constraint: constraint.clone(),
+                    name: c.id.clone(),
+                    pos,
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+fn parse_signature(signature: &str) -> Vec<ArgType> {
+    signature
+        .split(", ")
+        .map(|t| {
+            let array = t.starts_with('[');
+            let t = t.trim_start_matches('[').trim_end_matches(']');
+            let var = t.starts_with("var ");
+            let base = match t.trim_start_matches("var ") {
+                "bool" => BaseType::Bool,
+                "int" => BaseType::Int,
+                "float" => BaseType::Float,
+                _ => BaseType::SetOfInt,
+            };
+            ArgType {
+                base: Some(base),
+                array,
+                var,
+            }
+        })
+        .collect()
+}
+fn pred_par_type(t: &PredParType) -> ArgType {
+    match t {
+        PredParType::Basic(t) => basic_pred_par_type(t, false),
+        PredParType::Array { par_type, .. } => basic_pred_par_type(par_type, true),
+    }
+}
+fn basic_pred_par_type(t: &BasicPredParType, array: bool) -> ArgType {
+    let (base, var) = match t {
+        BasicPredParType::BasicParType(t) => (basic_par_type(t), false),
+        BasicPredParType::BasicVarType(t) => (basic_var_type(t), true),
+        BasicPredParType::VarSetOfInt => (BaseType::SetOfInt, true),
+        BasicPredParType::IntInRange(..) | BasicPredParType::IntInSet(_) => (BaseType::Int, false),
+        BasicPredParType::BoundedFloat(..) | BasicPredParType::FloatInSet(_) => {
+            (BaseType::Float, false)
+        }
+        BasicPredParType::SubSetOfIntSet(_) | BasicPredParType::SubSetOfIntRange(..) => {
+            (BaseType::SetOfInt, false)
+        }
+    };
+    ArgType {
+        base: Some(base),
+        array,
+        var,
+    }
+}
+fn basic_type(t: &BasicType) -> BaseType {
+    match t {
+        BasicType::Bool => BaseType::Bool,
+        BasicType::Int => BaseType::Int,
+        BasicType::Float => BaseType::Float,
+    }
+}
+fn basic_par_type(t: &BasicParType) -> BaseType {
+    match t {
+        BasicParType::BasicType(t) => basic_type(t),
+        BasicParType::SetOfInt => BaseType::SetOfInt,
+    }
+}
+fn basic_var_type(t: &BasicVarType) -> BaseType {
+    match t {
+        BasicVarType::BasicType(t) => basic_type(t),
+        BasicVarType::IntInRange(..) | BasicVarType::IntInSet(_) => BaseType::Int,
+        BasicVarType::BoundedFloat(..) => BaseType::Float,
+        BasicVarType::SubSetOfIntSet(_) | BasicVarType::SubSetOfIntRange(..) => BaseType::SetOfInt,
+    }
+}
+/// Returns the type of a constraint argument, resolving identifiers via the symbol table
+fn argument_type(e: &Expr, symbols: &SymbolTable) -> Option<ArgType> {
+    let scalar = |base| {
+        Some(ArgType {
+            base: Some(base),
+            array: false,
+            var: false,
+        })
+    };
+    match e {
+        Expr::VarParIdentifier(id) => symbols.get(id).map(|s| ArgType {
+            base: Some(s.base_type),
+            array: s.len.is_some(),
+            var: s.kind == SymbolKind::Variable,
+        }),
+        Expr::Bool(_) => scalar(BaseType::Bool),
+        Expr::Int(_) => scalar(BaseType::Int),
+        Expr::Float(_) => scalar(BaseType::Float),
+        Expr::Set(SetLiteralExpr::IntInRange(..)) | Expr::Set(SetLiteralExpr::SetInts(_)) => {
+            scalar(BaseType::SetOfInt)
+        }
+        Expr::Set(_) => None,
+        Expr::ArrayOfBool(v) => {
+            let mut base = None;
+            let mut var = false;
+            for e in v {
+                let (t, v) = match e {
+                    BoolExpr::Bool(_) => (BaseType::Bool, false),
+                    BoolExpr::VarParIdentifier(id) => element_type(id, symbols)?,
+                };
+                base.get_or_insert(t);
+                var |= v;
+            }
+            Some(ArgType {
+                base,
+                array: true,
+                var,
+            })
+        }
+        Expr::ArrayOfInt(v) => array_type(
+            BaseType::Int,
+            v.iter().map(|e| match e {
+                IntExpr::VarParIdentifier(id) => Some(id.as_str()),
+                IntExpr::Int(_) => None,
+            }),
+            symbols,
+        ),
+        Expr::ArrayOfFloat(v) => array_type(
+            BaseType::Float,
+            v.iter().map(|e| match e {
+                FloatExpr::VarParIdentifier(id) => Some(id.as_str()),
+                FloatExpr::Float(_) => None,
+            }),
+            symbols,
+        ),
+        Expr::ArrayOfSet(v) => array_type(
+            BaseType::SetOfInt,
+            v.iter().map(|e| match e {
+                SetExpr::VarParIdentifier(id) => Some(id.as_str()),
+                SetExpr::Set(_) => None,
+            }),
+            symbols,
+        ),
+    }
+}
+/// Returns the type of a scalar identifier and whether it is a variable
+fn element_type(id: &str, symbols: &SymbolTable) -> Option<(BaseType, bool)> {
+    match symbols.get(id) {
+        Some(s) if s.len.is_none() => Some((s.base_type, s.kind == SymbolKind::Variable)),
+        _ => None,
+    }
+}
+fn array_type<'a>(
+    base: BaseType,
+    ids: impl Iterator<Item = Option<&'a str>>,
+    symbols: &SymbolTable,
+) -> Option<ArgType> {
+    let mut var = false;
+    for id in ids.flatten() {
+        let (_, v) = element_type(id, symbols)?;
+        var |= v;
+    }
+    Some(ArgType {
+        base: Some(base),
+        array: true,
+        var,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an argument of the given type, with the variables declared in `symbols`
+    fn argument(t: &ArgType) -> Expr {
+        let id = match t.base {
+            Some(BaseType::Bool) => "b",
+            Some(BaseType::Int) => "x",
+            Some(BaseType::Float) => "f",
+            _ => "s",
+        };
+        let set = || SetLiteralExpr::SetInts(vec![IntExpr::Int(1)]);
+        match (t.array, t.var, t.base) {
+            (false, true, _) => Expr::VarParIdentifier(id.to_string()),
+            (true, true, _) => Expr::ArrayOfBool(vec![BoolExpr::VarParIdentifier(id.to_string())]),
+            (false, false, Some(BaseType::Bool)) => Expr::Bool(true),
+            (false, false, Some(BaseType::Int)) => Expr::Int(1),
+            (false, false, Some(BaseType::Float)) => Expr::Float(1.0),
+            (false, false, _) => Expr::Set(set()),
+            (true, false, Some(BaseType::Bool)) => Expr::ArrayOfBool(vec![BoolExpr::Bool(true)]),
+            (true, false, Some(BaseType::Int)) => Expr::ArrayOfInt(vec![IntExpr::Int(1)]),
+            (true, false, Some(BaseType::Float)) => Expr::ArrayOfFloat(vec![FloatExpr::Float(1.0)]),
+            (true, false, _) => Expr::ArrayOfSet(vec![SetExpr::Set(set())]),
+        }
+    }
+
+    #[test]
+    fn test_builtins_accepted() {
+        let config = Config {
+            check_signatures: true,
+            ..Default::default()
+        };
+        let mut symbols = SymbolTable::default();
+        for item in [
+            VarDeclItem::Bool {
+                id: "b".to_string(),
+                expr: None,
+                annos: vec![],
+            },
+            VarDeclItem::Int {
+                id: "x".to_string(),
+                expr: None,
+                annos: vec![],
+            },
+            VarDeclItem::Float {
+                id: "f".to_string(),
+                expr: None,
+                annos: vec![],
+            },
+            VarDeclItem::SetOfInt {
+                id: "s".to_string(),
+                expr: None,
+                annos: vec![],
+            },
+        ] {
+            symbols.declare_variable(&item, &config);
+        }
+        for (id, signature) in BUILTINS {
+            let c = ConstraintItem {
+                id: id.to_string(),
+                exprs: parse_signature(signature).iter().map(argument).collect(),
+                annos: vec![],
+            };
+            assert!(
+                check_constraint(&c, 1, &symbols, &config).is_ok(),
+                "{} is rejected",
+                id
+            );
+        }
+        for id in &[
+            "int_lin_le_imp",
+            "bool_clause_reif",
+            "bool_clause_imp",
+            "float_dom",
+            "float_in_reif",
+            "set_in_imp",
+        ] {
+            assert!(BUILTINS.iter().any(|(b, _)| b == id), "{} is missing", id);
+        }
+    }
+
+    #[test]
+    fn test_builtin_arities() {
+        let config = Config::default();
+        let mut symbols = SymbolTable::default();
+        symbols.declare_variable(
+            &VarDeclItem::Bool {
+                id: "b".to_string(),
+                expr: None,
+                annos: vec![],
+            },
+            &config,
+        );
+        let b = || Expr::VarParIdentifier("b".to_string());
+        for exprs in [vec![b(), b()], vec![b(), b(), b()]] {
+            let c = ConstraintItem {
+                id: "bool_xor".to_string(),
+                exprs,
+                annos: vec![],
+            };
+            assert!(check_constraint(&c, 1, &symbols, &config).is_ok());
+        }
+        let c = ConstraintItem {
+            id: "bool_xor".to_string(),
+            exprs: vec![b()],
+            annos: vec![],
+        };
+        assert_eq!(
+            check_constraint(&c, 1, &symbols, &config)
+                .unwrap_err()
+                .to_string(),
+            "Constraint c1 (bool_xor) expects 3 arguments, found 1"
+        );
+    }
+}
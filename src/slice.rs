@@ -0,0 +1,198 @@
+use crate::components::Components;
+use crate::incidence::{argument_ids, array_element_ids, assigned_id, objective_id};
+use crate::references::par_decl_id;
+use crate::{var_decl_id, FlatZincError};
+use anyhow::Result;
+use flatzinc::*;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+/// Statements of a model that can influence some variables
+///
+/// These are the statements of the connected components containing the variables,
+/// together with the parameters and predicates they refer to.
+#[derive(Debug, Default)]
+pub struct Slice {
+    keep: Vec<bool>,
+    variables: HashSet<String>,
+}
+impl Slice {
+    pub fn new(stmts: &[FzStmt], variables: &[String]) -> Result<Self> {
+        let mut components = Components::default();
+        for stmt in stmts {
+            components.add(stmt);
+        }
+        let assignment = components.assign();
+        let mut selected = HashSet::new();
+        for id in variables {
+            match assignment.variable(id) {
+                Some(k) => selected.insert(k),
+                None => return Err(FlatZincError::UnknownVariable { id: id.clone() }.into()),
+            };
+        }
+        let mut slice = Slice::default();
+        let mut references = HashSet::new();
+        let mut counter = 0;
+        for stmt in stmts {
+            let keep = match stmt {
+                FzStmt::Variable(d) => {
+                    let keep = assignment
+                        .variable(var_decl_id(d))
+                        .is_some_and(|k| selected.contains(&k));
+                    if keep {
+                        slice.variables.insert(var_decl_id(d).to_string());
+                        match array_element_ids(d) {
+                            Some((_, elements)) => references.extend(elements),
+                            None => references.extend(assigned_id(d)),
+                        }
+                    }
+                    keep
+                }
+                FzStmt::Constraint(c) => {
+                    counter += 1;
+                    let keep = assignment
+                        .constraint(counter)
+                        .is_some_and(|k| selected.contains(&k));
+                    if keep {
+                        references.insert(c.id.as_str());
+                        for e in &c.exprs {
+                            let mut ids = vec![];
+                            argument_ids(e, &mut ids);
+                            references.extend(ids);
+                        }
+                    }
+                    keep
+                }
+                FzStmt::SolveItem(_) => true,
+                _ => false,
+            };
+            slice.keep.push(keep);
+        }
+        for (stmt, keep) in stmts.iter().zip(slice.keep.iter_mut()) {
+            match stmt {
+                FzStmt::Predicate(p) => *keep = references.contains(p.id.as_str()),
+                FzStmt::Parameter(p) => *keep = references.contains(par_decl_id(p)),
+                _ => {}
+            }
+        }
+        Ok(slice)
+    }
+    /// Returns true if the statement with the given index is part of the slice
+    pub fn contains(&self, i: usize) -> bool {
+        self.keep.get(i).copied().unwrap_or(false)
+    }
+    /// Returns the solve item of the slice, which keeps the objective
+    /// only if its variable is part of the slice and drops all search annotations
+    pub fn solve_item(&self, i: &SolveItem) -> SolveItem {
+        let goal = match objective_id(&i.goal) {
+            Some(id) if self.variables.contains(id) => i.goal.clone(),
+            _ => Goal::Satisfy,
+        };
+        SolveItem {
+            goal,
+            annotations: vec![],
+        }
+    }
+    /// Writes the lines of the FlatZinc model that are part of the slice
+    pub fn write_fzn(
+        &self,
+        mut out: impl Write,
+        stmts: &[FzStmt],
+        lines: &[String],
+    ) -> io::Result<()> {
+        for (i, (stmt, line)) in stmts.iter().zip(lines).enumerate() {
+            if !self.contains(i) {
+                continue;
+            }
+            match stmt {
+                FzStmt::SolveItem(item) => match self.solve_item(item).goal {
+                    Goal::OptimizeBool(t, BoolExpr::VarParIdentifier(id))
+                    | Goal::OptimizeInt(t, IntExpr::VarParIdentifier(id))
+                    | Goal::OptimizeFloat(t, FloatExpr::VarParIdentifier(id))
+                    | Goal::OptimizeSet(t, SetExpr::VarParIdentifier(id)) => {
+                        let t = match t {
+                            OptimizationType::Minimize => "minimize",
+                            OptimizationType::Maximize => "maximize",
+                        };
+                        writeln!(out, "solve {} {};", t, id)?
+                    }
+                    _ => writeln!(out, "solve satisfy;")?,
+                },
+                _ => writeln!(out, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_fz_stmt;
+
+    const MODEL: &[&str] = &[
+        "predicate my_le(var int: a,var int: b);",
+        "int: n = 2;",
+        "int: m = 3;",
+        "var 1..3: x;",
+        "var 1..3: y;",
+        "var 1..3: z;",
+        "var 1..3: u;",
+        "constraint my_le(x,y);",
+        "constraint int_lin_le([1,1],[y,z],n);",
+        "constraint int_le(u,m);",
+        "solve minimize u;",
+    ];
+    fn stmts(lines: &[&str]) -> Vec<FzStmt> {
+        lines.iter().map(|s| parse_fz_stmt(s).unwrap().0).collect()
+    }
+
+    #[test]
+    fn test_reachability() {
+        let slice = Slice::new(&stmts(MODEL), &["x".to_string()]).unwrap();
+        let kept: Vec<_> = (0..MODEL.len()).filter(|i| slice.contains(*i)).collect();
+        // z is reached from x over y, only the referenced predicate and parameter are kept
+        assert_eq!(kept, vec![0, 1, 3, 4, 5, 7, 8, 10]);
+        let slice = Slice::new(&stmts(MODEL), &["u".to_string()]).unwrap();
+        let kept: Vec<_> = (0..MODEL.len()).filter(|i| slice.contains(*i)).collect();
+        assert_eq!(kept, vec![2, 6, 9, 10]);
+        assert!(!slice.contains(MODEL.len()));
+    }
+    #[test]
+    fn test_unknown_variable() {
+        let err = Slice::new(&stmts(MODEL), &["w".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            FlatZincError::UnknownVariable {
+                id: "w".to_string()
+            }
+            .to_string()
+        );
+    }
+    #[test]
+    fn test_solve_item() {
+        let stmts = stmts(MODEL);
+        let solve = match &stmts[10] {
+            FzStmt::SolveItem(i) => i,
+            _ => unreachable!(),
+        };
+        let slice = Slice::new(&stmts, &["u".to_string()]).unwrap();
+        assert_eq!(slice.solve_item(solve).goal, solve.goal);
+        let slice = Slice::new(&stmts, &["x".to_string()]).unwrap();
+        assert_eq!(slice.solve_item(solve).goal, Goal::Satisfy);
+        let lines: Vec<_> = MODEL.iter().map(|s| s.to_string()).collect();
+        let mut res = Vec::new();
+        slice.write_fzn(&mut res, &stmts, &lines).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&res).unwrap(),
+            "predicate my_le(var int: a,var int: b);\n\
+             int: n = 2;\n\
+             var 1..3: x;\n\
+             var 1..3: y;\n\
+             var 1..3: z;\n\
+             constraint my_le(x,y);\n\
+             constraint int_lin_le([1,1],[y,z],n);\n\
+             solve satisfy;\n"
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::duplicates::Duplicates;
+use crate::json;
+use flatzinc::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Size statistics of a model, collected in a single pass over its statements
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub predicates: usize,
+    /// Number of parameters by type, e.g. `int` or `array of int`
+    pub parameters: BTreeMap<String, usize>,
+    /// Number of variables by type and domain shape, e.g. `int range` or `array of bool`
+    pub variables: BTreeMap<String, usize>,
+    /// Number of scalar variables with a finite domain,
+    /// by the smallest power of two not below the size of the domain
+    pub domain_sizes: BTreeMap<u128, usize>,
+    /// Number of parameter and variable arrays by length
+    pub array_lengths: BTreeMap<usize, usize>,
+    /// Number of constraints by name
+    pub constraints: BTreeMap<String, usize>,
+    /// Number of constraints identical to an earlier constraint
+    pub duplicate_constraints: usize,
+    /// Number of parameters and variables annotated with `output_var`
+    pub output_vars: usize,
+    /// Number of parameters and variables annotated with `output_array`
+    pub output_arrays: usize,
+    /// Kind of the objective, e.g. `satisfy` or `minimize int`
+    pub objective: Option<String>,
+    /// Types of the scalar variables, since the parser can not tell the type of an objective
+    var_types: HashMap<String, &'static str>,
+    duplicates: Duplicates,
+}
+impl Stats {
+    pub fn add(&mut self, stmt: &FzStmt, par_annos: &[Annotation]) {
+        match stmt {
+            FzStmt::Comment(_) => {}
+            FzStmt::Predicate(_) => self.predicates += 1,
+            FzStmt::Parameter(p) => {
+                let (key, len) = par_decl_type(p);
+                *self.parameters.entry(key.to_string()).or_default() += 1;
+                if let Some(len) = len {
+                    *self.array_lengths.entry(len).or_default() += 1;
+                }
+                self.annotations(par_annos);
+            }
+            FzStmt::Variable(d) => {
+                let (key, len, size, annos) = var_decl_type(d);
+                if len.is_none() {
+                    let (id, base) = scalar_base_type(d);
+                    self.var_types.insert(id.to_string(), base);
+                }
+                *self.variables.entry(key).or_default() += 1;
+                if let Some(len) = len {
+                    *self.array_lengths.entry(len).or_default() += 1;
+                }
+                if let Some(size) = size {
+                    *self
+                        .domain_sizes
+                        .entry(size.checked_next_power_of_two().unwrap_or(size))
+                        .or_default() += 1;
+                }
+                self.annotations(annos);
+            }
+            FzStmt::Constraint(c) => {
+                *self.constraints.entry(c.id.clone()).or_default() += 1;
+                // only the number of duplicates is needed, not their first occurrences
+                if self.duplicates.check(c, 0).is_some() {
+                    self.duplicate_constraints += 1;
+                }
+            }
+            FzStmt::SolveItem(i) => {
+                self.objective = Some(match &i.goal {
+                    Goal::Satisfy => "satisfy".to_string(),
+                    Goal::OptimizeBool(t, BoolExpr::VarParIdentifier(id)) => format!(
+                        "{} {}",
+                        opt_type(t),
+                        self.var_types.get(id).unwrap_or(&"bool")
+                    ),
+                    Goal::OptimizeBool(t, _) => format!("{} bool", opt_type(t)),
+                    Goal::OptimizeInt(t, _) => format!("{} int", opt_type(t)),
+                    Goal::OptimizeFloat(t, _) => format!("{} float", opt_type(t)),
+                    Goal::OptimizeSet(t, _) => format!("{} set of int", opt_type(t)),
+                })
+            }
+        }
+    }
+    fn annotations(&mut self, annos: &[Annotation]) {
+        if annos.iter().any(|a| a.id == "output_var") {
+            self.output_vars += 1;
+        }
+        if annos.iter().any(|a| a.id == "output_array") {
+            self.output_arrays += 1;
+        }
+    }
+    pub fn write_text(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "predicates: {}", self.predicates)?;
+        writeln!(
+            out,
+            "parameters: {}",
+            self.parameters.values().sum::<usize>()
+        )?;
+        for (t, n) in &self.parameters {
+            writeln!(out, "  {}: {}", t, n)?;
+        }
+        writeln!(out, "variables: {}", self.variables.values().sum::<usize>())?;
+        for (t, n) in &self.variables {
+            writeln!(out, "  {}: {}", t, n)?;
+        }
+        writeln!(out, "domain sizes:")?;
+        for (size, n) in &self.domain_sizes {
+            writeln!(out, "  <= {}: {}", size, n)?;
+        }
+        writeln!(out, "array lengths:")?;
+        for (len, n) in &self.array_lengths {
+            writeln!(out, "  {}: {}", len, n)?;
+        }
+        writeln!(
+            out,
+            "constraints: {}",
+            self.constraints.values().sum::<usize>()
+        )?;
+        for (name, n) in &self.constraints {
+            writeln!(out, "  {}: {}", name, n)?;
+        }
+        writeln!(out, "duplicate constraints: {}", self.duplicate_constraints)?;
+        writeln!(out, "output variables: {}", self.output_vars)?;
+        writeln!(out, "output arrays: {}", self.output_arrays)?;
+        writeln!(
+            out,
+            "objective: {}",
+            self.objective.as_deref().unwrap_or("none")
+        )?;
+        Ok(())
+    }
+    pub fn write_json(&self, mut out: impl Write) -> io::Result<()> {
+        let objective = match &self.objective {
+            Some(o) => json::string(o),
+            None => "null".to_string(),
+        };
+        writeln!(
+            out,
+            "{}",
+            json::object(vec![
+                ("predicates", self.predicates.to_string()),
+                ("parameters", counts(&self.parameters)),
+                ("variables", counts(&self.variables)),
+                ("domain_sizes", json::object(&self.domain_sizes)),
+                ("array_lengths", json::object(&self.array_lengths)),
+                ("constraints", counts(&self.constraints)),
+                (
+                    "duplicate_constraints",
+                    self.duplicate_constraints.to_string()
+                ),
+                ("output_vars", self.output_vars.to_string()),
+                ("output_arrays", self.output_arrays.to_string()),
+                ("objective", objective),
+            ])
+        )
+    }
+}
+/// Returns the total and the individual counts as JSON object
+fn counts(map: &BTreeMap<String, usize>) -> String {
+    json::object(vec![
+        ("total", map.values().sum::<usize>().to_string()),
+        ("by_type", json::object(map)),
+    ])
+}
+fn opt_type(t: &OptimizationType) -> &'static str {
+    match t {
+        OptimizationType::Minimize => "minimize",
+        OptimizationType::Maximize => "maximize",
+    }
+}
+/// Returns the identifier and the base type of a scalar variable declaration
+fn scalar_base_type(item: &VarDeclItem) -> (&str, &'static str) {
+    match item {
+        VarDeclItem::Bool { id, .. } => (id, "bool"),
+        VarDeclItem::Int { id, .. }
+        | VarDeclItem::IntInRange { id, .. }
+        | VarDeclItem::IntInSet { id, .. } => (id, "int"),
+        VarDeclItem::Float { id, .. } | VarDeclItem::BoundedFloat { id, .. } => (id, "float"),
+        VarDeclItem::SetOfInt { id, .. }
+        | VarDeclItem::SubSetOfIntRange { id, .. }
+        | VarDeclItem::SubSetOfIntSet { id, .. } => (id, "set of int"),
+        VarDeclItem::ArrayOfBool { id, .. }
+        | VarDeclItem::ArrayOfInt { id, .. }
+        | VarDeclItem::ArrayOfIntInRange { id, .. }
+        | VarDeclItem::ArrayOfIntInSet { id, .. }
+        | VarDeclItem::ArrayOfFloat { id, .. }
+        | VarDeclItem::ArrayOfBoundedFloat { id, .. }
+        | VarDeclItem::ArrayOfSet { id, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntRange { id, .. }
+        | VarDeclItem::ArrayOfSubSetOfIntSet { id, .. } => (id, "array"),
+    }
+}
+fn par_decl_type(item: &ParDeclItem) -> (&'static str, Option<usize>) {
+    match item {
+        ParDeclItem::Bool { .. } => ("bool", None),
+        ParDeclItem::Int { .. } => ("int", None),
+        ParDeclItem::Float { .. } => ("float", None),
+        ParDeclItem::SetOfInt { .. } => ("set of int", None),
+        ParDeclItem::ArrayOfBool { v, .. } => ("array of bool", Some(v.len())),
+        ParDeclItem::ArrayOfInt { v, .. } => ("array of int", Some(v.len())),
+        ParDeclItem::ArrayOfFloat { v, .. } => ("array of float", Some(v.len())),
+        ParDeclItem::ArrayOfSet { v, .. } => ("array of set of int", Some(v.len())),
+    }
+}
+/// Returns the type and domain shape, the array length, the domain size and the annotations
+/// of a variable declaration
+fn var_decl_type(item: &VarDeclItem) -> (String, Option<usize>, Option<u128>, &[Annotation]) {
+    let range_size = |lb: i128, ub: i128| {
+        Some(if ub < lb {
+            0
+        } else {
+            ub.abs_diff(lb).saturating_add(1)
+        })
+    };
+    let set_size = |set: &[i128]| Some(set.iter().collect::<HashSet<_>>().len() as u128);
+    let (key, len, size, annos) = match item {
+        VarDeclItem::Bool { annos, .. } => ("bool", None, Some(2), annos),
+        VarDeclItem::Int { annos, .. } => ("int", None, None, annos),
+        VarDeclItem::IntInRange { lb, ub, annos, .. } => {
+            ("int range", None, range_size(*lb, *ub), annos)
+        }
+        VarDeclItem::IntInSet { set, annos, .. } => ("int set", None, set_size(set), annos),
+        VarDeclItem::Float { annos, .. } => ("float", None, None, annos),
+        VarDeclItem::BoundedFloat { annos, .. } => ("float range", None, None, annos),
+        VarDeclItem::SetOfInt { annos, .. } => ("set of int", None, None, annos),
+        VarDeclItem::SubSetOfIntRange { annos, .. } => ("set of int range", None, None, annos),
+        VarDeclItem::SubSetOfIntSet { annos, .. } => ("set of int set", None, None, annos),
+        VarDeclItem::ArrayOfBool {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of bool", Some(*len), None, annos),
+        VarDeclItem::ArrayOfInt {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of int", Some(*len), None, annos),
+        VarDeclItem::ArrayOfIntInRange {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of int range", Some(*len), None, annos),
+        VarDeclItem::ArrayOfIntInSet {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of int set", Some(*len), None, annos),
+        VarDeclItem::ArrayOfFloat {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of float", Some(*len), None, annos),
+        VarDeclItem::ArrayOfBoundedFloat {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of float range", Some(*len), None, annos),
+        VarDeclItem::ArrayOfSet {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of set of int", Some(*len), None, annos),
+        VarDeclItem::ArrayOfSubSetOfIntRange {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of set of int range", Some(*len), None, annos),
+        VarDeclItem::ArrayOfSubSetOfIntSet {
+            ix: IndexSet(len),
+            annos,
+            ..
+        } => ("array of set of int set", Some(*len), None, annos),
+    };
+    (key.to_string(), len.map(|l| l as usize), size, annos)
+}
@@ -0,0 +1,117 @@
+use flatzinc::FzStmt;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Names of the constraints supported by an encoding
+#[derive(Debug, Default)]
+pub struct SupportedConstraints {
+    names: HashSet<String>,
+}
+impl SupportedConstraints {
+    /// Reads a list with one constraint name per line,
+    /// empty lines and lines starting with `%` or `#` are ignored
+    pub fn add_list(&mut self, text: &str) {
+        for line in text.lines() {
+            let name = line.trim();
+            if !name.is_empty() && !name.starts_with('%') && !name.starts_with('#') {
+                self.names.insert(name.trim_matches('"').to_string());
+            }
+        }
+    }
+    /// Extracts the names matched by patterns like `constraint(C,"int_lin_le")` in an encoding
+    pub fn add_encoding(&mut self, text: &str) {
+        let pattern = "constraint(";
+        let mut rest = text;
+        while let Some(start) = rest.find(pattern) {
+            let preceded_by_name = rest[..start]
+                .chars()
+                .last()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+            rest = &rest[start + pattern.len()..];
+            if preceded_by_name {
+                continue;
+            }
+            if let Some(name) = second_argument_string(rest) {
+                self.names.insert(name.to_string());
+            }
+        }
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+/// Returns the string given as second argument in `args`, like `C,"name")`
+fn second_argument_string(args: &str) -> Option<&str> {
+    let mut depth = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                let rest = args[i + 1..].trim_start().strip_prefix('"')?;
+                let end = rest.find('"')?;
+                return Some(&rest[..end]);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Constraint of a model that is not supported by an encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedConstraint {
+    pub name: String,
+    /// Number of occurrences in the model
+    pub count: usize,
+    /// Line of the first occurrence
+    pub first_line: usize,
+}
+
+/// Collects the constraints of a model that are not supported by an encoding
+#[derive(Debug, Default)]
+pub struct SupportReport {
+    unsupported: Vec<UnsupportedConstraint>,
+    index: HashMap<String, usize>,
+}
+impl SupportReport {
+    pub fn check(&mut self, stmt: &FzStmt, line: usize, supported: &SupportedConstraints) {
+        if let FzStmt::Constraint(c) = stmt {
+            if supported.contains(&c.id) {
+                return;
+            }
+            match self.index.get(&c.id) {
+                Some(i) => self.unsupported[*i].count += 1,
+                None => {
+                    self.index.insert(c.id.clone(), self.unsupported.len());
+                    self.unsupported.push(UnsupportedConstraint {
+                        name: c.id.clone(),
+                        count: 1,
+                        first_line: line,
+                    });
+                }
+            }
+        }
+    }
+    /// Returns the unsupported constraints in the order of their first occurrence
+    pub fn unsupported(&self) -> &[UnsupportedConstraint] {
+        &self.unsupported
+    }
+    pub fn write(&self, mut out: impl Write) -> io::Result<()> {
+        for u in &self.unsupported {
+            writeln!(
+                out,
+                "{}: {} occurrence{}, first in line {}",
+                u.name,
+                u.count,
+                if u.count == 1 { "" } else { "s" },
+                u.first_line
+            )?;
+        }
+        Ok(())
+    }
+}
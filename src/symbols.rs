@@ -0,0 +1,264 @@
+use crate::domain::{declared_values, Values};
+use crate::incidence::Incidence;
+use crate::Config;
+use flatzinc::*;
+use std::collections::{HashMap, HashSet};
+
+/// Element type of a declared parameter or variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseType {
+    Bool,
+    Int,
+    Float,
+    SetOfInt,
+}
+impl BaseType {
+    pub fn name(self) -> &'static str {
+        match self {
+            BaseType::Bool => "bool",
+            BaseType::Int => "int",
+            BaseType::Float => "float",
+            BaseType::SetOfInt => "set_of_int",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Parameter,
+    Variable,
+}
+impl SymbolKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            SymbolKind::Parameter => "par",
+            SymbolKind::Variable => "var",
+        }
+    }
+}
+
+/// Declared type of an identifier
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub base_type: BaseType,
+    /// Length of the array, `None` for scalars
+    pub len: Option<usize>,
+}
+impl Symbol {
+    /// Returns the type as term, e.g. `var(int)` or `par(array(3,int))`
+    pub fn type_term(&self) -> String {
+        let t = match self.len {
+            Some(len) => format!("array({},{})", len, self.base_type.name()),
+            None => self.base_type.name().to_string(),
+        };
+        format!("{}({})", self.kind.name(), t)
+    }
+}
+
+/// Declared predicates, parameters and variables of a model
+///
+/// The type of every identifier is always recorded, while the values of parameters,
+/// the elements of variable arrays and the incidence data are only kept
+/// if an option in the `Config` needs them.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    predicates: HashMap<String, PredicateItem>,
+    symbols: HashMap<String, Symbol>,
+    /// Declarations of the parameters, kept for `inline_parameters`
+    parameters: HashMap<String, ParDeclItem>,
+    /// Elements of the variable arrays, kept for `expand_arrays`
+    arrays: HashMap<String, Expr>,
+    aliases: HashMap<String, String>,
+    /// Domains of the scalar variables, kept for `resolve_aliases`
+    domains: HashMap<String, Values>,
+    /// Kept for `incidence`
+    incidence: Incidence,
+    /// Variables with a `#show` directive
+    shown: HashSet<String>,
+}
+impl SymbolTable {
+    pub fn get(&self, id: &str) -> Option<&Symbol> {
+        self.symbols.get(id)
+    }
+    /// Returns the declaration, and thereby the value, of a parameter
+    pub fn parameter(&self, id: &str) -> Option<&ParDeclItem> {
+        self.parameters.get(id)
+    }
+    /// Returns the elements of a variable array
+    pub fn variable_array(&self, id: &str) -> Option<&Expr> {
+        self.arrays.get(id)
+    }
+    /// Returns the representative of the equivalence class of aliased variables containing `id`
+    pub fn representative<'a>(&'a self, id: &'a str) -> &'a str {
+        self.aliases.get(id).map_or(id, |r| r.as_str())
+    }
+    /// Returns the declared domain of a scalar variable
+    pub(crate) fn domain(&self, id: &str) -> Option<&Values> {
+        self.domains.get(id)
+    }
+    /// Records that a `#show` directive was written for `id`,
+    /// returns false if one was already written
+    pub(crate) fn mark_shown(&mut self, id: &str) -> bool {
+        self.shown.insert(id.to_string())
+    }
+    /// Records `id` as alias of `representative`, which must not be an alias itself
+    pub fn declare_alias(&mut self, id: &str, representative: &str) {
+        self.aliases
+            .insert(id.to_string(), representative.to_string());
+    }
+    /// Returns the distinct variables referenced by a constraint,
+    /// including the elements of referenced variable arrays
+    pub fn constraint_variables<'a>(&'a self, c: &'a ConstraintItem) -> Vec<&'a str> {
+        self.incidence.constraint_variables(c)
+    }
+    pub fn predicate(&self, id: &str) -> Option<&PredicateItem> {
+        self.predicates.get(id)
+    }
+    pub fn declare_predicate(&mut self, item: &PredicateItem) {
+        self.predicates.insert(item.id.clone(), item.clone());
+    }
+    pub fn declare_parameter(&mut self, item: &ParDeclItem, config: &Config) {
+        let (id, base_type, len) = match item {
+            ParDeclItem::Bool { id, .. } => (id, BaseType::Bool, None),
+            ParDeclItem::Int { id, .. } => (id, BaseType::Int, None),
+            ParDeclItem::Float { id, .. } => (id, BaseType::Float, None),
+            ParDeclItem::SetOfInt { id, .. } => (id, BaseType::SetOfInt, None),
+            ParDeclItem::ArrayOfBool { id, v, .. } => (id, BaseType::Bool, Some(v.len())),
+            ParDeclItem::ArrayOfInt { id, v, .. } => (id, BaseType::Int, Some(v.len())),
+            ParDeclItem::ArrayOfFloat { id, v, .. } => (id, BaseType::Float, Some(v.len())),
+            ParDeclItem::ArrayOfSet { id, v, .. } => (id, BaseType::SetOfInt, Some(v.len())),
+        };
+        self.symbols.insert(
+            id.clone(),
+            Symbol {
+                kind: SymbolKind::Parameter,
+                base_type,
+                len,
+            },
+        );
+        if config.inline_parameters {
+            self.parameters.insert(id.clone(), item.clone());
+        }
+    }
+    pub fn declare_variable(&mut self, item: &VarDeclItem, config: &Config) {
+        let (id, base_type, len) = match item {
+            VarDeclItem::Bool { id, .. } => (id, BaseType::Bool, None),
+            VarDeclItem::Int { id, .. }
+            | VarDeclItem::IntInRange { id, .. }
+            | VarDeclItem::IntInSet { id, .. } => (id, BaseType::Int, None),
+            VarDeclItem::Float { id, .. } | VarDeclItem::BoundedFloat { id, .. } => {
+                (id, BaseType::Float, None)
+            }
+            VarDeclItem::SetOfInt { id, .. }
+            | VarDeclItem::SubSetOfIntRange { id, .. }
+            | VarDeclItem::SubSetOfIntSet { id, .. } => (id, BaseType::SetOfInt, None),
+            VarDeclItem::ArrayOfBool {
+                id,
+                ix: IndexSet(len),
+                ..
+            } => (id, BaseType::Bool, Some(*len as usize)),
+            VarDeclItem::ArrayOfInt {
+                id,
+                ix: IndexSet(len),
+                ..
+            }
+            | VarDeclItem::ArrayOfIntInRange {
+                id,
+                ix: IndexSet(len),
+                ..
+            }
+            | VarDeclItem::ArrayOfIntInSet {
+                id,
+                ix: IndexSet(len),
+                ..
+            } => (id, BaseType::Int, Some(*len as usize)),
+            VarDeclItem::ArrayOfFloat {
+                id,
+                ix: IndexSet(len),
+                ..
+            }
+            | VarDeclItem::ArrayOfBoundedFloat {
+                id,
+                ix: IndexSet(len),
+                ..
+            } => (id, BaseType::Float, Some(*len as usize)),
+            VarDeclItem::ArrayOfSet {
+                id,
+                ix: IndexSet(len),
+                ..
+            }
+            | VarDeclItem::ArrayOfSubSetOfIntRange {
+                id,
+                ix: IndexSet(len),
+                ..
+            }
+            | VarDeclItem::ArrayOfSubSetOfIntSet {
+                id,
+                ix: IndexSet(len),
+                ..
+            } => (id, BaseType::SetOfInt, Some(*len as usize)),
+        };
+        self.symbols.insert(
+            id.clone(),
+            Symbol {
+                kind: SymbolKind::Variable,
+                base_type,
+                len,
+            },
+        );
+        if config.resolve_aliases && len.is_none() {
+            self.domains.insert(id.clone(), declared_values(item));
+        }
+        if config.expand_arrays {
+            if let Some(elements) = array_elements(item) {
+                self.arrays.insert(id.clone(), elements);
+            }
+        }
+        if config.incidence {
+            self.incidence.declare(item);
+        }
+    }
+}
+
+fn array_elements(item: &VarDeclItem) -> Option<Expr> {
+    match item {
+        VarDeclItem::ArrayOfBool {
+            array_expr: Some(ArrayOfBoolExpr::Array(v)),
+            ..
+        } => Some(Expr::ArrayOfBool(v.clone())),
+        VarDeclItem::ArrayOfInt {
+            array_expr: Some(ArrayOfIntExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfIntInRange {
+            array_expr: Some(ArrayOfIntExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfIntInSet {
+            array_expr: Some(ArrayOfIntExpr::Array(v)),
+            ..
+        } => Some(Expr::ArrayOfInt(v.clone())),
+        VarDeclItem::ArrayOfFloat {
+            array_expr: Some(ArrayOfFloatExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfBoundedFloat {
+            array_expr: Some(ArrayOfFloatExpr::Array(v)),
+            ..
+        } => Some(Expr::ArrayOfFloat(v.clone())),
+        VarDeclItem::ArrayOfSet {
+            array_expr: Some(ArrayOfSetExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfSubSetOfIntRange {
+            array_expr: Some(ArrayOfSetExpr::Array(v)),
+            ..
+        }
+        | VarDeclItem::ArrayOfSubSetOfIntSet {
+            array_expr: Some(ArrayOfSetExpr::Array(v)),
+            ..
+        } => Some(Expr::ArrayOfSet(v.clone())),
+        _ => None,
+    }
+}